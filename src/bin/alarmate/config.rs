@@ -0,0 +1,242 @@
+use serde::{Deserialize, Deserializer};
+
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use alarmate::{Area, Error, Fingerprint, Result};
+
+use crate::output::Format;
+use crate::password::PasswordSource;
+
+/// The config file, e.g.
+///
+/// ```toml
+/// default_profile = "home"
+///
+/// [profiles.home]
+/// host = "192.168.178.10"
+/// username = "admin"
+/// password_env = "HOME_PANEL_PASSWORD"
+/// fingerprint = "AB:CD:…"
+///
+/// [profiles.office]
+/// host = "https://office.example.com/lupusec/action/"
+/// username = "admin"
+/// password_command = "pass show lupusec/office"
+/// output = "json"
+/// area = "area2"
+/// ```
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// The profile used when `--profile` is not given
+    default_profile: Option<String>,
+
+    /// The named profiles
+    #[serde(default)]
+    profiles: BTreeMap<String, Profile>,
+}
+
+/// Connection settings and defaults for one panel. Command line flags and
+/// `ALARMATE_*` environment variables take precedence.
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    /// The host name, IP address or base URL (`https://…/action/`) of the
+    /// panel
+    pub host: Option<String>,
+
+    /// The user name
+    pub username: Option<String>,
+
+    /// The environment variable holding the password
+    pub password_env: Option<String>,
+
+    /// The file whose first line is the password
+    pub password_file: Option<PathBuf>,
+
+    /// The shell command printing the password, e.g. `pass show lupusec`
+    pub password_command: Option<String>,
+
+    /// The SHA-256 fingerprint of the panel's TLS certificate to pin
+    #[serde(default, deserialize_with = "from_str")]
+    pub fingerprint: Option<Fingerprint>,
+
+    /// The default output format
+    pub output: Option<Format>,
+
+    /// The default area of `alarmate mode` and `alarmate readiness`
+    #[serde(default, deserialize_with = "from_str")]
+    pub area: Option<Area>,
+
+    /// Whether `alarmate mode` refuses to arm while devices are open or
+    /// faulty (override with `--force`)
+    pub check_readiness: Option<bool>,
+
+    /// The default poll interval in seconds of `alarmate watch` and
+    /// `alarmate mqtt`
+    pub interval: Option<u64>,
+}
+
+impl Config {
+    /// Load the config file at `path`, or at the default location if `path`
+    /// is `None`. A missing file at the default location is an empty config.
+    pub fn load(path: Option<&Path>) -> Result<Config> {
+        let (path, explicit) = match path {
+            Some(path) => (path.to_owned(), true),
+            None => match default_path() {
+                Some(path) => (path, false),
+                None => return Ok(Config::default()),
+            },
+        };
+
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound && !explicit => {
+                return Ok(Config::default());
+            }
+            Err(e) => return Err(config_error(&path, e)),
+        };
+
+        toml::from_str(&text).map_err(|e| config_error(&path, e))
+    }
+
+    /// The profile named `name`, else the default profile, else an empty
+    /// profile.
+    pub fn profile(mut self, name: Option<&str>) -> Result<Profile> {
+        let Some(name) = name.or(self.default_profile.as_deref()) else {
+            return Ok(Profile::default());
+        };
+
+        self.profiles
+            .remove(name)
+            .ok_or_else(|| Error::InvalidArgument(format!("no profile named {name}")))
+    }
+}
+
+impl Profile {
+    /// Where the password comes from: `password_env`, `password_file` or
+    /// `password_command`, of which at most one may be set.
+    pub fn password_source(&self) -> Result<Option<PasswordSource>> {
+        let sources: Vec<_> = [
+            self.password_env.clone().map(PasswordSource::Env),
+            self.password_file.clone().map(PasswordSource::File),
+            self.password_command.clone().map(PasswordSource::Command),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+
+        match <[_; 1]>::try_from(sources) {
+            Ok([source]) => Ok(Some(source)),
+            Err(sources) if sources.is_empty() => Ok(None),
+            Err(_) => Err(Error::InvalidArgument(
+                "set only one of password_env, password_file and password_command".into(),
+            )),
+        }
+    }
+}
+
+/// `$XDG_CONFIG_HOME/alarmate/config.toml`, falling back to
+/// `~/.config/alarmate/config.toml`.
+pub fn default_path() -> Option<PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| Some(PathBuf::from(std::env::var_os("HOME")?).join(".config")))?;
+
+    Some(config_home.join("alarmate").join("config.toml"))
+}
+
+fn config_error(path: &Path, error: impl Display) -> Error {
+    Error::Io(io::Error::other(format!(
+        "cannot read config file {}: {error}",
+        path.display()
+    )))
+}
+
+/// Deserialize an optional value from a string via its `FromStr` impl.
+fn from_str<'de, D, T>(deserializer: D) -> std::result::Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    let Some(s) = Option::<String>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+    s.parse().map(Some).map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+        default_profile = "home"
+
+        [profiles.home]
+        host = "192.168.178.10"
+        username = "admin"
+
+        [profiles.office]
+        host = "office.example.com"
+        password_env = "ALARMATE_TEST_OFFICE_PASSWORD"
+        fingerprint = "00:11:22:33:44:55:66:77:88:99:AA:BB:CC:DD:EE:FF:00:11:22:33:44:55:66:77:88:99:AA:BB:CC:DD:EE:FF"
+        output = "json"
+        area = "area2"
+        interval = 30
+    "#;
+
+    #[test]
+    fn select_profiles() {
+        let config: Config = toml::from_str(CONFIG).unwrap();
+        let home = config.profile(None).unwrap();
+        assert_eq!(home.host.as_deref(), Some("192.168.178.10"));
+        assert_eq!(home.username.as_deref(), Some("admin"));
+
+        let config: Config = toml::from_str(CONFIG).unwrap();
+        let office = config.profile(Some("office")).unwrap();
+        assert_eq!(office.host.as_deref(), Some("office.example.com"));
+        assert!(office.fingerprint.is_some());
+        assert!(matches!(office.output, Some(Format::Json)));
+        assert_eq!(office.area, Some(Area::Area2));
+        assert_eq!(office.interval, Some(30));
+
+        let config: Config = toml::from_str(CONFIG).unwrap();
+        assert!(config.profile(Some("garage")).is_err());
+    }
+
+    #[test]
+    fn empty_config_has_empty_profile() {
+        let profile = Config::default().profile(None).unwrap();
+        assert!(profile.host.is_none());
+        assert!(profile.password_source().unwrap().is_none());
+    }
+
+    #[test]
+    fn one_password_source() {
+        let profile: Profile = toml::from_str("password_command = \"pass show lupusec\"").unwrap();
+        assert!(matches!(
+            profile.password_source().unwrap(),
+            Some(PasswordSource::Command(_))
+        ));
+
+        let profile: Profile =
+            toml::from_str("password_env = \"PW\"\npassword_file = \"/run/pw\"").unwrap();
+        assert!(profile.password_source().is_err());
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        assert!(toml::from_str::<Config>("[profiles.home]\npasword = \"x\"").is_err());
+    }
+
+    #[test]
+    fn missing_explicit_config_fails() {
+        assert!(Config::load(Some(Path::new("/nonexistent/alarmate.toml"))).is_err());
+    }
+}
@@ -0,0 +1,684 @@
+mod config;
+mod output;
+mod password;
+
+use clap::Parser;
+use futures::StreamExt;
+use serde::Serialize;
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use alarmate::{
+    Area, Client, ClientBuilder, Error, EventKind, Fingerprint, HistoryFilter, Mode, Result,
+    ShutterCommand, ThermostatMode,
+};
+
+use config::{Config, Profile};
+use output::Format;
+use password::PasswordSource;
+
+/// The poll interval of `watch` and `mqtt` if neither a flag nor the profile
+/// sets one
+const DEFAULT_INTERVAL: u64 = 5;
+
+#[derive(Parser, Debug)]
+struct ConnectionArgs {
+    /// The host name, IP address or base URL of the panel
+    #[arg(value_name = "HOST", env = "ALARMATE_IP_ADDRESS", short = 'I')]
+    host: Option<String>,
+
+    /// The password (visible to other users, prefer the options below)
+    #[arg(
+        value_name = "PASSWORD",
+        env = "ALARMATE_PASSWORD",
+        short = 'P',
+        group = "password_source"
+    )]
+    password: Option<String>,
+
+    /// Read the password from the first line of this file
+    #[arg(long, value_name = "PATH", group = "password_source")]
+    password_file: Option<PathBuf>,
+
+    /// Read the password from the first line of standard input
+    #[arg(long, group = "password_source")]
+    password_stdin: bool,
+
+    /// Read the password from the first line printed by this shell command,
+    /// e.g. `pass show lupusec`
+    #[arg(long, value_name = "COMMAND", group = "password_source")]
+    password_command: Option<String>,
+
+    /// The user name
+    #[arg(value_name = "USERNAME", env = "ALARMATE_USERNAME", short = 'U')]
+    username: Option<String>,
+
+    /// The SHA-256 fingerprint of the panel's TLS certificate to pin
+    #[arg(long, env = "ALARMATE_FINGERPRINT")]
+    fingerprint: Option<Fingerprint>,
+
+    /// Append all requests to the panel and its responses to this fixture
+    /// file, without credentials and tokens
+    #[arg(long, value_name = "PATH")]
+    record: Option<PathBuf>,
+
+    /// Answer requests from this fixture file instead of contacting the
+    /// panel
+    #[arg(long, value_name = "PATH", conflicts_with = "record")]
+    replay: Option<PathBuf>,
+}
+
+impl ConnectionArgs {
+    /// Create a [`Client`] from these connection arguments, falling back to
+    /// the settings of `profile`.
+    fn into_client(self, profile: &Profile) -> Result<Client> {
+        self.into_builder(profile)?.build()
+    }
+
+    /// Create a [`ClientBuilder`] from these connection arguments, falling
+    /// back to the settings of `profile`. Without a password from either, it
+    /// is prompted for on the terminal.
+    fn into_builder(self, profile: &Profile) -> Result<ClientBuilder> {
+        if let Some(path) = self.replay {
+            return Ok(Client::builder("", "").replay(path));
+        }
+
+        let missing = |what: &str, flag: &str, var: &str| {
+            Error::InvalidArgument(format!(
+                "no {what} given: pass {flag}, set {var} or configure it in a profile"
+            ))
+        };
+
+        let host = self
+            .host
+            .or_else(|| profile.host.clone())
+            .ok_or_else(|| missing("panel host", "-I", "ALARMATE_IP_ADDRESS"))?;
+        let username = self
+            .username
+            .or_else(|| profile.username.clone())
+            .ok_or_else(|| missing("user name", "-U", "ALARMATE_USERNAME"))?;
+        // At most one of these is set, as they share the `password_source`
+        // group
+        let source = if self.password_stdin {
+            Some(PasswordSource::Stdin)
+        } else {
+            self.password_file
+                .map(PasswordSource::File)
+                .or(self.password_command.map(PasswordSource::Command))
+        };
+        let password = match (source, self.password) {
+            (Some(source), _) => source.read()?,
+            (None, Some(password)) => password.into(),
+            (None, None) => match profile.password_source()? {
+                Some(source) => source.read()?,
+                None => password::prompt(&username, &host)?.ok_or_else(|| {
+                    missing(
+                        "password",
+                        "-P, --password-file, --password-stdin or --password-command",
+                        "ALARMATE_PASSWORD",
+                    )
+                })?,
+            },
+        };
+
+        let builder = Client::builder(&username, password);
+        let builder = if host.contains("://") {
+            builder.base_url(host)
+        } else {
+            builder.host(host)
+        };
+        let builder = match self.fingerprint.or(profile.fingerprint) {
+            Some(fingerprint) => builder.pin_certificate(fingerprint),
+            None => builder,
+        };
+        Ok(match self.record {
+            Some(path) => builder.record(path),
+            None => builder,
+        })
+    }
+}
+
+#[cfg(feature = "mqtt")]
+#[derive(Parser, Debug)]
+struct BrokerArgs {
+    /// The host name of the MQTT broker
+    #[arg(long, env = "ALARMATE_MQTT_HOST", default_value = "localhost")]
+    mqtt_host: String,
+
+    /// The port of the MQTT broker
+    #[arg(long, env = "ALARMATE_MQTT_PORT", default_value_t = 1883)]
+    mqtt_port: u16,
+
+    /// The user name for the MQTT broker
+    #[arg(long, env = "ALARMATE_MQTT_USERNAME", requires = "mqtt_password")]
+    mqtt_username: Option<String>,
+
+    /// The password for the MQTT broker
+    #[arg(long, env = "ALARMATE_MQTT_PASSWORD", requires = "mqtt_username")]
+    mqtt_password: Option<String>,
+
+    /// The MQTT client ID
+    #[arg(long, default_value = "alarmate")]
+    client_id: String,
+
+    /// The topic all other topics are nested below
+    #[arg(long, default_value = "alarmate")]
+    base_topic: String,
+
+    /// Seconds between polls of the panel [default: 5]
+    #[arg(short, long, value_name = "SECONDS")]
+    interval: Option<u64>,
+
+    /// Publish Home Assistant discovery configs below this prefix
+    #[arg(
+        long,
+        value_name = "PREFIX",
+        num_args = 0..=1,
+        default_missing_value = "homeassistant"
+    )]
+    home_assistant: Option<String>,
+}
+
+#[cfg(feature = "mqtt")]
+impl BrokerArgs {
+    /// Create a [`Bridge`](alarmate::mqtt::Bridge) from these broker
+    /// arguments.
+    fn into_bridge(self, client: Client, profile: &Profile) -> alarmate::mqtt::Bridge {
+        let mut options =
+            alarmate::mqtt::MqttOptions::new(self.client_id, self.mqtt_host, self.mqtt_port);
+        if let (Some(username), Some(password)) = (self.mqtt_username, self.mqtt_password) {
+            options.set_credentials(username, password);
+        }
+
+        let bridge = alarmate::mqtt::Bridge::new(client, options)
+            .base_topic(self.base_topic)
+            .interval(Duration::from_secs(
+                self.interval
+                    .or(profile.interval)
+                    .unwrap_or(DEFAULT_INTERVAL),
+            ));
+
+        match self.home_assistant {
+            Some(prefix) => bridge.home_assistant(prefix),
+            None => bridge,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum SwitchState {
+    On,
+    Off,
+}
+
+/// The columns of `watch` events in tables and CSV
+const EVENT_COLUMNS: &[&str] = &[
+    "event",
+    "area",
+    "from",
+    "to",
+    "alarm",
+    "entry_delay",
+    "exit_delay",
+    "alert",
+    "message",
+    "sid",
+    "name",
+    "kind",
+    "state",
+    "battery",
+    "tamper",
+    "condition",
+];
+
+#[derive(Parser, Debug)]
+struct Cli {
+    /// The output format [default: table]
+    #[arg(value_enum, short, long, global = true)]
+    output: Option<Format>,
+
+    /// The config file [default: $XDG_CONFIG_HOME/alarmate/config.toml]
+    #[arg(long, env = "ALARMATE_CONFIG", global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// The profile of the config file to use [default: its `default_profile`]
+    #[arg(long, env = "ALARMATE_PROFILE", global = true, value_name = "NAME")]
+    profile: Option<String>,
+
+    /// Log panel requests to stderr; repeat for more detail (-vv)
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+
+    #[command(subcommand)]
+    command: Opt,
+}
+
+/// The switch state that was requested, as printed by `alarmate switch`
+#[derive(Serialize)]
+struct SwitchChange {
+    sid: String,
+    on: bool,
+}
+
+/// The shutter command that was sent, as printed by `alarmate shutter`
+#[derive(Serialize)]
+struct ShutterChange {
+    sid: String,
+    command: String,
+}
+
+/// The dimmer level that was requested, as printed by `alarmate dimmer`
+#[derive(Serialize)]
+struct DimmerChange {
+    sid: String,
+    level: u8,
+}
+
+/// The mode that was requested, as printed by `alarmate mode`
+#[derive(Serialize)]
+struct ModeChange {
+    area: Area,
+    mode: Mode,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Opt {
+    /// List devices
+    #[command(name = "devices")]
+    Devices {
+        #[command(flatten)]
+        conn: ConnectionArgs,
+    },
+
+    /// Get current status
+    #[command(name = "status")]
+    Status {
+        #[command(flatten)]
+        conn: ConnectionArgs,
+    },
+
+    /// List power switches and smart plugs
+    #[command(name = "switches")]
+    Switches {
+        #[command(flatten)]
+        conn: ConnectionArgs,
+    },
+
+    /// Turn a power switch or smart plug on or off
+    #[command(name = "switch")]
+    Switch {
+        #[command(flatten)]
+        conn: ConnectionArgs,
+
+        /// The sensor ID of the switch
+        #[arg(value_name = "SID")]
+        sid: String,
+
+        /// The new state
+        #[arg(value_enum, ignore_case = true, value_name = "STATE")]
+        state: SwitchState,
+    },
+
+    /// List thermostats and radiator valves
+    #[command(name = "thermostats")]
+    Thermostats {
+        #[command(flatten)]
+        conn: ConnectionArgs,
+    },
+
+    /// Set the target temperature or mode of a thermostat
+    #[command(name = "thermostat")]
+    Thermostat {
+        #[command(flatten)]
+        conn: ConnectionArgs,
+
+        /// The sensor ID of the thermostat
+        #[arg(value_name = "SID")]
+        sid: String,
+
+        /// The target temperature in °C
+        #[arg(short, long, value_name = "CELSIUS")]
+        target: Option<f64>,
+
+        /// The operating mode
+        #[arg(value_enum, ignore_case = true, short, long)]
+        mode: Option<ThermostatMode>,
+    },
+
+    /// List shutters and awnings
+    #[command(name = "shutters")]
+    Shutters {
+        #[command(flatten)]
+        conn: ConnectionArgs,
+    },
+
+    /// Open, close, stop or position a shutter or awning
+    #[command(name = "shutter")]
+    Shutter {
+        #[command(flatten)]
+        conn: ConnectionArgs,
+
+        /// The sensor ID of the shutter
+        #[arg(value_name = "SID")]
+        sid: String,
+
+        /// `open`, `close`, `stop` or a position in percent (0 = closed)
+        #[arg(value_name = "COMMAND")]
+        command: ShutterCommand,
+    },
+
+    /// List dimmers
+    #[command(name = "dimmers")]
+    Dimmers {
+        #[command(flatten)]
+        conn: ConnectionArgs,
+    },
+
+    /// Set the brightness of a dimmer
+    #[command(name = "dimmer")]
+    Dimmer {
+        #[command(flatten)]
+        conn: ConnectionArgs,
+
+        /// The sensor ID of the dimmer
+        #[arg(value_name = "SID")]
+        sid: String,
+
+        /// The brightness in percent (0 = off)
+        #[arg(value_name = "LEVEL", value_parser = clap::value_parser!(u8).range(0..=100))]
+        level: u8,
+    },
+
+    /// Show the event history
+    #[command(name = "history")]
+    History {
+        #[command(flatten)]
+        conn: ConnectionArgs,
+
+        /// Only show events of this kind (can be repeated)
+        #[arg(value_enum, ignore_case = true, short, long)]
+        kind: Vec<EventKind>,
+    },
+
+    /// Print changes of area modes and device states as they happen
+    #[command(name = "watch")]
+    Watch {
+        #[command(flatten)]
+        conn: ConnectionArgs,
+
+        /// Seconds between polls [default: 5]
+        #[arg(short, long, value_name = "SECONDS")]
+        interval: Option<u64>,
+    },
+
+    /// Serve Prometheus metrics of the panel on /metrics
+    #[cfg(feature = "exporter")]
+    #[command(name = "exporter")]
+    Exporter {
+        #[command(flatten)]
+        conn: ConnectionArgs,
+
+        /// The address to listen on; only local clients can connect by
+        /// default, use e.g. `0.0.0.0:9798` to expose the metrics
+        #[arg(
+            long,
+            env = "ALARMATE_EXPORTER_LISTEN",
+            default_value = "127.0.0.1:9798",
+            value_name = "ADDRESS"
+        )]
+        listen: std::net::SocketAddr,
+    },
+
+    /// Bridge the panel to an MQTT broker
+    #[cfg(feature = "mqtt")]
+    #[command(name = "mqtt")]
+    Mqtt {
+        #[command(flatten)]
+        conn: ConnectionArgs,
+
+        #[command(flatten)]
+        broker: BrokerArgs,
+    },
+
+    /// List open, faulty or unreadable devices that stand in the way of arming an area
+    #[command(name = "readiness")]
+    Readiness {
+        #[command(flatten)]
+        conn: ConnectionArgs,
+
+        /// The area (e.g. `area1` or `3`) [default: area1]
+        #[arg(short, long)]
+        area: Option<Area>,
+    },
+
+    /// Change mode
+    #[command(name = "mode")]
+    Mode {
+        #[command(flatten)]
+        conn: ConnectionArgs,
+
+        /// The area (e.g. `area1` or `3`) [default: area1]
+        #[arg(short, long)]
+        area: Option<Area>,
+
+        /// The mode
+        #[arg(value_enum, ignore_case = true, value_name = "MODE")]
+        mode: Mode,
+
+        /// Seconds to wait for the panel to report the new mode
+        #[arg(long, value_name = "SECONDS", default_value_t = 30)]
+        timeout: u64,
+
+        /// Return as soon as the panel accepted the change, without waiting
+        /// for the new mode
+        #[arg(long, conflicts_with = "timeout")]
+        no_verify: bool,
+
+        /// Refuse to arm while devices of the area are open, faulty or cannot
+        /// be checked
+        #[arg(long)]
+        check: bool,
+
+        /// Arm even if `check_readiness` is enabled in the profile and
+        /// devices are open, faulty or cannot be checked
+        #[arg(long, conflicts_with = "check")]
+        force: bool,
+    },
+}
+
+/// Print alarmate's tracing events to stderr, at debug level with `-v` and
+/// trace level with `-vv`.
+fn init_logging(verbose: u8) {
+    use std::io::IsTerminal;
+    use tracing_subscriber::filter::Targets;
+    use tracing_subscriber::prelude::*;
+
+    let level = match verbose {
+        0 => return,
+        1 => tracing::Level::DEBUG,
+        _ => tracing::Level::TRACE,
+    };
+
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(std::io::stderr)
+                .with_ansi(std::io::stderr().is_terminal()),
+        )
+        .with(Targets::new().with_target("alarmate", level))
+        .init();
+}
+
+#[tokio::main]
+async fn main() -> Result {
+    let cli = Cli::parse();
+    init_logging(cli.verbose);
+    let profile = Config::load(cli.config.as_deref())?.profile(cli.profile.as_deref())?;
+    let output = cli.output.or(profile.output).unwrap_or_default();
+    let command = cli.command;
+
+    match command {
+        Opt::Devices { conn } => {
+            let client = conn.into_client(&profile)?;
+            let devices = client.list_devices().await?;
+            output::print(output, &devices)?;
+        }
+
+        Opt::Status { conn } => {
+            let client = conn.into_client(&profile)?;
+            let status = client.get_status().await?;
+            output::print_rows(output, &status, &status.areas)?;
+        }
+
+        Opt::Switches { conn } => {
+            let client = conn.into_client(&profile)?;
+            let switches = client.list_switches().await?;
+            output::print(output, &switches)?;
+        }
+
+        Opt::Switch { conn, sid, state } => {
+            let client = conn.into_client(&profile)?;
+            let on = matches!(state, SwitchState::On);
+            client.set_switch(&sid, on).await?;
+            output::print(output, &SwitchChange { sid, on })?;
+        }
+
+        Opt::Thermostats { conn } => {
+            let client = conn.into_client(&profile)?;
+            let thermostats = client.list_thermostats().await?;
+            output::print(output, &thermostats)?;
+        }
+
+        Opt::Thermostat {
+            conn,
+            sid,
+            target,
+            mode,
+        } => {
+            let client = conn.into_client(&profile)?;
+            if let Some(mode) = mode {
+                client.set_thermostat_mode(&sid, mode).await?;
+            }
+            if let Some(target) = target {
+                client.set_target_temperature(&sid, target).await?;
+            }
+            let thermostat = client.thermostat(&sid).await?;
+            output::print(output, &thermostat)?;
+        }
+
+        Opt::Shutters { conn } => {
+            let client = conn.into_client(&profile)?;
+            let shutters = client.list_shutters().await?;
+            output::print(output, &shutters)?;
+        }
+
+        Opt::Shutter { conn, sid, command } => {
+            let client = conn.into_client(&profile)?;
+            client.move_shutter(&sid, command).await?;
+            let command = command.to_string();
+            output::print(output, &ShutterChange { sid, command })?;
+        }
+
+        Opt::Dimmers { conn } => {
+            let client = conn.into_client(&profile)?;
+            let dimmers = client.list_dimmers().await?;
+            output::print(output, &dimmers)?;
+        }
+
+        Opt::Dimmer { conn, sid, level } => {
+            let client = conn.into_client(&profile)?;
+            client.set_dimmer_level(&sid, level).await?;
+            output::print(output, &DimmerChange { sid, level })?;
+        }
+
+        Opt::History { conn, kind } => {
+            let client = conn.into_client(&profile)?;
+            let filter = kind
+                .into_iter()
+                .fold(HistoryFilter::default(), HistoryFilter::kind);
+            let history = client.history(&filter).await?;
+            output::print(output, &history)?;
+        }
+
+        Opt::Watch { conn, interval } => {
+            let client = conn.into_client(&profile)?;
+            let interval = interval.or(profile.interval).unwrap_or(DEFAULT_INTERVAL);
+            let mut events = std::pin::pin!(client.watch(Duration::from_secs(interval)));
+            let mut stream = output::Stream::new(output, EVENT_COLUMNS);
+            while let Some(event) = events.next().await {
+                match event {
+                    Ok(event) => stream.print(&event)?,
+                    Err(e) => eprintln!("{e}"),
+                }
+            }
+        }
+
+        #[cfg(feature = "exporter")]
+        Opt::Exporter { conn, listen } => {
+            let client = conn.into_client(&profile)?;
+            alarmate::exporter::Exporter::new(client)
+                .serve(listen)
+                .await?;
+        }
+
+        #[cfg(feature = "mqtt")]
+        Opt::Mqtt { conn, broker } => {
+            let client = conn.into_client(&profile)?;
+            broker.into_bridge(client, &profile).run().await?;
+        }
+
+        Opt::Readiness { conn, area } => {
+            let client = conn.into_client(&profile)?;
+            let area = area.or(profile.area).unwrap_or(Area::Area1);
+            let readiness = client.arm_readiness(area).await?;
+            output::print_rows(output, &readiness, &readiness.blockers)?;
+        }
+
+        Opt::Mode {
+            conn,
+            mode,
+            area,
+            timeout,
+            no_verify,
+            check,
+            force,
+        } => {
+            let guard = !force && (check || profile.check_readiness == Some(true));
+            let mut builder = conn.into_builder(&profile)?.guard_arming(guard);
+            if !no_verify {
+                builder = builder.verify_mode_change(Duration::from_secs(timeout));
+            }
+            let client = builder.build()?;
+            let area = area.or(profile.area).unwrap_or(Area::Area1);
+            client.change_mode(area, mode).await?;
+            output::print(output, &ModeChange { area, mode })?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn password_sources_conflict() {
+        let parse = |args: &[&str]| {
+            Cli::try_parse_from(
+                ["alarmate", "status", "-I", "panel", "-U", "admin"]
+                    .iter()
+                    .chain(args),
+            )
+        };
+
+        assert!(parse(&["-P", "hunter2"]).is_ok());
+        assert!(parse(&["--password-stdin"]).is_ok());
+
+        let error = parse(&["-P", "hunter2", "--password-stdin"]).unwrap_err();
+        assert_eq!(error.kind(), clap::error::ErrorKind::ArgumentConflict);
+        let error = parse(&["--password-file", "pw", "--password-command", "pass"]).unwrap_err();
+        assert_eq!(error.kind(), clap::error::ErrorKind::ArgumentConflict);
+    }
+}
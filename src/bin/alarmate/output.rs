@@ -0,0 +1,186 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use std::io::{self, Write};
+
+use alarmate::{Error, Result};
+
+/// How results are printed
+#[derive(clap::ValueEnum, Deserialize, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// An aligned table of the scalar fields
+    #[default]
+    Table,
+    /// Pretty-printed JSON (one compact object per line for `watch`)
+    Json,
+    /// YAML (one document per event for `watch`)
+    Yaml,
+    /// CSV with a header row, of the scalar fields
+    Csv,
+}
+
+/// Print `value` in the given format. Tables and CSV show one row per
+/// element if `value` serializes to a list, otherwise a single row.
+pub fn print(format: Format, value: &impl Serialize) -> Result {
+    print_rows(format, value, value)
+}
+
+/// Print `value` as JSON or YAML, and `rows` as table or CSV, for values
+/// that do not map to rows as a whole.
+pub fn print_rows(format: Format, value: &impl Serialize, rows: &impl Serialize) -> Result {
+    let mut out = io::stdout().lock();
+
+    match format {
+        Format::Json => writeln!(out, "{}", serde_json::to_string_pretty(value)?)?,
+        Format::Yaml => write!(
+            out,
+            "{}",
+            serde_yaml::to_string(value).map_err(io::Error::other)?
+        )?,
+        Format::Table => {
+            let rows = to_rows(rows)?;
+            let columns = columns(&rows);
+            write_table(&mut out, &columns, &rows)?;
+        }
+        Format::Csv => {
+            let rows = to_rows(rows)?;
+            let columns = columns(&rows);
+            write_csv(&mut out, &columns, &rows, true)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints a stream of values one at a time.
+pub struct Stream {
+    format: Format,
+    columns: &'static [&'static str],
+    header: bool,
+}
+
+impl Stream {
+    /// Stream values in `format`, using `columns` for tables and CSV.
+    pub fn new(format: Format, columns: &'static [&'static str]) -> Stream {
+        Stream {
+            format,
+            columns,
+            header: true,
+        }
+    }
+
+    /// Print the next value.
+    pub fn print(&mut self, value: &impl Serialize) -> Result {
+        let mut out = io::stdout().lock();
+        let header = std::mem::take(&mut self.header);
+
+        match self.format {
+            Format::Json => writeln!(out, "{}", serde_json::to_string(value)?)?,
+            Format::Yaml => {
+                let yaml = serde_yaml::to_string(value).map_err(io::Error::other)?;
+                write!(out, "---\n{yaml}")?;
+            }
+            Format::Table => {
+                let row = serde_json::to_value(value)?;
+                let fields: Vec<_> = self
+                    .columns
+                    .iter()
+                    .filter_map(|column| Some((column, cell(row.get(column)?)?)))
+                    .filter(|(_, cell)| !cell.is_empty())
+                    .map(|(column, cell)| format!("{column}={cell}"))
+                    .collect();
+                writeln!(out, "{}", fields.join("  "))?;
+            }
+            Format::Csv => {
+                let rows = [serde_json::to_value(value)?];
+                write_csv(&mut out, self.columns, &rows, header)?;
+            }
+        }
+
+        out.flush()?;
+        Ok(())
+    }
+}
+
+fn to_rows(value: &impl Serialize) -> Result<Vec<Value>> {
+    Ok(match serde_json::to_value(value)? {
+        Value::Array(rows) => rows,
+        row => vec![row],
+    })
+}
+
+/// The scalar fields of all rows, in the order they first appear.
+fn columns(rows: &[Value]) -> Vec<&str> {
+    let mut columns = Vec::new();
+    for row in rows.iter().filter_map(Value::as_object) {
+        for (key, value) in row {
+            if cell(value).is_some() && !columns.contains(&key.as_str()) {
+                columns.push(key.as_str());
+            }
+        }
+    }
+    columns
+}
+
+/// The text of a scalar value, `None` for lists and objects.
+fn cell(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => Some(String::new()),
+        Value::String(s) => Some(s.clone()),
+        Value::Bool(_) | Value::Number(_) => Some(value.to_string()),
+        Value::Array(_) | Value::Object(_) => None,
+    }
+}
+
+fn cells(columns: &[&str], row: &Value) -> Vec<String> {
+    columns
+        .iter()
+        .map(|column| row.get(column).and_then(cell).unwrap_or_default())
+        .collect()
+}
+
+fn write_table(out: &mut impl Write, columns: &[&str], rows: &[Value]) -> io::Result<()> {
+    if columns.is_empty() {
+        return Ok(());
+    }
+
+    let header: Vec<_> = columns.iter().map(|c| c.to_ascii_uppercase()).collect();
+    let rows: Vec<_> = rows.iter().map(|row| cells(columns, row)).collect();
+
+    let mut widths: Vec<_> = header.iter().map(|h| h.chars().count()).collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    for line in std::iter::once(&header).chain(&rows) {
+        let padded: Vec<_> = line
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:width$}"))
+            .collect();
+        writeln!(out, "{}", padded.join("  ").trim_end())?;
+    }
+
+    Ok(())
+}
+
+fn write_csv(out: &mut impl Write, columns: &[&str], rows: &[Value], header: bool) -> Result {
+    let mut writer = csv::Writer::from_writer(out);
+    if header {
+        writer.write_record(columns).map_err(csv_error)?;
+    }
+    for row in rows {
+        writer
+            .write_record(cells(columns, row))
+            .map_err(csv_error)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn csv_error(e: csv::Error) -> Error {
+    Error::Io(io::Error::other(e))
+}
@@ -0,0 +1,134 @@
+use zeroize::Zeroizing;
+
+use std::io::{self, BufRead, IsTerminal};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use alarmate::{Error, Password, Result};
+
+/// Where the panel password is read from
+#[derive(Debug, Clone)]
+pub enum PasswordSource {
+    /// The environment variable of this name
+    Env(String),
+
+    /// The first line of this file
+    File(PathBuf),
+
+    /// The first line of standard input
+    Stdin,
+
+    /// The first line printed by this shell command, e.g. `pass show lupusec`
+    Command(String),
+}
+
+impl PasswordSource {
+    /// Read the password.
+    pub fn read(&self) -> Result<Password> {
+        let text = match self {
+            PasswordSource::Env(var) => Zeroizing::new(std::env::var(var).map_err(|_| {
+                Error::InvalidArgument(format!("environment variable {var} is not set"))
+            })?),
+            PasswordSource::File(path) => {
+                Zeroizing::new(std::fs::read_to_string(path).map_err(|e| {
+                    io::Error::new(
+                        e.kind(),
+                        format!("cannot read password file {}: {e}", path.display()),
+                    )
+                })?)
+            }
+            PasswordSource::Stdin => {
+                let mut line = Zeroizing::new(String::new());
+                io::stdin().lock().read_line(&mut line)?;
+                line
+            }
+            PasswordSource::Command(command) => run(command)?,
+        };
+
+        match text.lines().next() {
+            Some(line) if !line.is_empty() => Ok(Password::from(line)),
+            _ => Err(Error::InvalidArgument(format!(
+                "empty password from {self}"
+            ))),
+        }
+    }
+}
+
+impl std::fmt::Display for PasswordSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PasswordSource::Env(var) => write!(f, "environment variable {var}"),
+            PasswordSource::File(path) => write!(f, "password file {}", path.display()),
+            PasswordSource::Stdin => f.write_str("standard input"),
+            PasswordSource::Command(command) => write!(f, "password command `{command}`"),
+        }
+    }
+}
+
+/// Ask for the password without echoing it, if standard input is a terminal.
+pub fn prompt(username: &str, host: &str) -> Result<Option<Password>> {
+    if !io::stdin().is_terminal() {
+        return Ok(None);
+    }
+
+    let password = rpassword::prompt_password(format!("Password for {username}@{host}: "))?;
+    Ok(Some(Password::from(password)))
+}
+
+/// Run `command` in the shell and return what it printed. Standard input and
+/// error are inherited so the command can ask for a passphrase.
+fn run(command: &str) -> Result<Zeroizing<String>> {
+    #[cfg(windows)]
+    let mut shell = Command::new("cmd");
+    #[cfg(windows)]
+    shell.arg("/C");
+    #[cfg(not(windows))]
+    let mut shell = Command::new("sh");
+    #[cfg(not(windows))]
+    shell.arg("-c");
+
+    let output = shell
+        .arg(command)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .output()?;
+    let stdout = Zeroizing::new(output.stdout);
+
+    if !output.status.success() {
+        return Err(Error::InvalidArgument(format!(
+            "password command `{command}` failed: {}",
+            output.status
+        )));
+    }
+
+    let text = std::str::from_utf8(&stdout).map_err(|_| {
+        Error::InvalidArgument(format!(
+            "password command `{command}` printed invalid UTF-8"
+        ))
+    })?;
+    Ok(Zeroizing::new(text.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_line_of_file() {
+        let path = std::env::temp_dir().join(format!("alarmate-password-{}", std::process::id()));
+        std::fs::write(&path, "hunter2\nurl: https://panel\n").unwrap();
+        let password = PasswordSource::File(path.clone()).read();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(password.unwrap().expose(), "hunter2");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn output_of_command() {
+        let source = PasswordSource::Command("printf 'hunter2\\n'".into());
+        assert_eq!(source.read().unwrap().expose(), "hunter2");
+
+        assert!(PasswordSource::Command("exit 1".into()).read().is_err());
+        assert!(PasswordSource::Command("true".into()).read().is_err());
+    }
+}
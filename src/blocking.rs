@@ -0,0 +1,241 @@
+//! A blocking client for synchronous code
+//!
+//! [`blocking::Client`](Client) has the same methods as the async
+//! [`Client`](crate::Client), but waits for the panel's answer instead of
+//! returning a future:
+//!
+//! ```rust,no_run
+//! use alarmate::{Area, Mode};
+//!
+//! let client = alarmate::blocking::Client::new("admin", "changeme", "192.168.178.10".parse().unwrap())?;
+//! client.change_mode(Area::Area1, Mode::Disarmed)?;
+//! println!("{:?}", client.get_status()?);
+//! # Ok::<(), alarmate::Error>(())
+//! ```
+//!
+//! It wraps an async client and runs it on a private single-threaded tokio
+//! runtime, so token caching, session timeout handling, retries and
+//! certificate pinning behave exactly the same. Like `reqwest::blocking`, it
+//! must not be used from within an async runtime.
+
+use futures::StreamExt;
+
+use std::future::Future;
+use std::net::Ipv4Addr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::builder::ClientBuilder;
+use crate::constants::{Area, Mode, ThermostatMode};
+use crate::errors::Result;
+use crate::password::Password;
+use crate::readiness::ArmReadiness;
+use crate::resources::{
+    devices::Device,
+    dimmers::Dimmer,
+    history::{HistoryEntry, HistoryFilter},
+    panel::Modes,
+    shutters::{Shutter, ShutterCommand},
+    switches::Switch,
+    thermostats::Thermostat,
+};
+use crate::tls::Fingerprint;
+use crate::watch::Event;
+
+/// A blocking version of [`alarmate::Client`](crate::Client)
+///
+/// Clones share the session token and the runtime.
+#[derive(Clone, Debug)]
+pub struct Client {
+    client: crate::Client,
+    runtime: Arc<tokio::runtime::Runtime>,
+}
+
+impl Client {
+    /// Construct a client, see [`Client::new`](crate::Client::new).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the underlying HTTP client or the runtime cannot
+    /// be built.
+    pub fn new(username: &str, password: &str, ip_address: Ipv4Addr) -> Result<Client> {
+        Client::from_async(crate::Client::new(username, password, ip_address)?)
+    }
+
+    /// Create a [`ClientBuilder`]; finish it with
+    /// [`build_blocking`](ClientBuilder::build_blocking).
+    pub fn builder(username: &str, password: impl Into<Password>) -> ClientBuilder {
+        crate::Client::builder(username, password)
+    }
+
+    /// Wrap an async client.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the runtime cannot be built.
+    pub fn from_async(client: crate::Client) -> Result<Client> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+
+        Ok(Client {
+            client,
+            runtime: Arc::new(runtime),
+        })
+    }
+
+    /// See [`Client::certificate_fingerprint`](crate::Client::certificate_fingerprint).
+    pub fn certificate_fingerprint(&self) -> Option<Fingerprint> {
+        self.client.certificate_fingerprint()
+    }
+
+    /// See [`Client::get_status`](crate::Client::get_status).
+    pub fn get_status(&self) -> Result<Modes> {
+        self.block_on(self.client.get_status())
+    }
+
+    /// See [`Client::change_mode`](crate::Client::change_mode).
+    pub fn change_mode(&self, area: Area, mode: Mode) -> Result {
+        self.block_on(self.client.change_mode(area, mode))
+    }
+
+    /// See [`Client::force_change_mode`](crate::Client::force_change_mode).
+    pub fn force_change_mode(&self, area: Area, mode: Mode) -> Result {
+        self.block_on(self.client.force_change_mode(area, mode))
+    }
+
+    /// See [`Client::arm_readiness`](crate::Client::arm_readiness).
+    pub fn arm_readiness(&self, area: Area) -> Result<ArmReadiness> {
+        self.block_on(self.client.arm_readiness(area))
+    }
+
+    /// See [`Client::list_devices`](crate::Client::list_devices).
+    pub fn list_devices(&self) -> Result<Vec<Device>> {
+        self.block_on(self.client.list_devices())
+    }
+
+    /// See [`Client::list_switches`](crate::Client::list_switches).
+    pub fn list_switches(&self) -> Result<Vec<Switch>> {
+        self.block_on(self.client.list_switches())
+    }
+
+    /// See [`Client::switch`](crate::Client::switch).
+    pub fn switch(&self, sid: &str) -> Result<Switch> {
+        self.block_on(self.client.switch(sid))
+    }
+
+    /// See [`Client::set_switch`](crate::Client::set_switch).
+    pub fn set_switch(&self, sid: &str, on: bool) -> Result {
+        self.block_on(self.client.set_switch(sid, on))
+    }
+
+    /// See [`Client::list_thermostats`](crate::Client::list_thermostats).
+    pub fn list_thermostats(&self) -> Result<Vec<Thermostat>> {
+        self.block_on(self.client.list_thermostats())
+    }
+
+    /// See [`Client::thermostat`](crate::Client::thermostat).
+    pub fn thermostat(&self, sid: &str) -> Result<Thermostat> {
+        self.block_on(self.client.thermostat(sid))
+    }
+
+    /// See [`Client::set_target_temperature`](crate::Client::set_target_temperature).
+    pub fn set_target_temperature(&self, sid: &str, celsius: f64) -> Result {
+        self.block_on(self.client.set_target_temperature(sid, celsius))
+    }
+
+    /// See [`Client::set_thermostat_mode`](crate::Client::set_thermostat_mode).
+    pub fn set_thermostat_mode(&self, sid: &str, mode: ThermostatMode) -> Result {
+        self.block_on(self.client.set_thermostat_mode(sid, mode))
+    }
+
+    /// See [`Client::list_shutters`](crate::Client::list_shutters).
+    pub fn list_shutters(&self) -> Result<Vec<Shutter>> {
+        self.block_on(self.client.list_shutters())
+    }
+
+    /// See [`Client::move_shutter`](crate::Client::move_shutter).
+    pub fn move_shutter(&self, sid: &str, command: ShutterCommand) -> Result {
+        self.block_on(self.client.move_shutter(sid, command))
+    }
+
+    /// See [`Client::list_dimmers`](crate::Client::list_dimmers).
+    pub fn list_dimmers(&self) -> Result<Vec<Dimmer>> {
+        self.block_on(self.client.list_dimmers())
+    }
+
+    /// See [`Client::set_dimmer_level`](crate::Client::set_dimmer_level).
+    pub fn set_dimmer_level(&self, sid: &str, level: u8) -> Result {
+        self.block_on(self.client.set_dimmer_level(sid, level))
+    }
+
+    /// See [`Client::history`](crate::Client::history).
+    pub fn history(&self, filter: &HistoryFilter) -> Result<Vec<HistoryEntry>> {
+        self.block_on(self.client.history(filter))
+    }
+
+    /// See [`Client::watch`](crate::Client::watch). Each call to `next`
+    /// blocks until the next change.
+    pub fn watch(&self, interval: Duration) -> impl Iterator<Item = Result<Event>> + use<> {
+        let runtime = Arc::clone(&self.runtime);
+        let mut events = Box::pin(self.client.watch(interval));
+        std::iter::from_fn(move || runtime.block_on(events.next()))
+    }
+
+    /// The async client this client wraps.
+    pub fn as_async(&self) -> &crate::Client {
+        &self.client
+    }
+
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+}
+
+impl ClientBuilder {
+    /// Build a [`blocking::Client`](Client).
+    ///
+    /// # Errors
+    ///
+    /// See [`build`](ClientBuilder::build); also fails if the runtime cannot
+    /// be built.
+    pub fn build_blocking(self) -> Result<Client> {
+        Client::from_async(self.build()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::tests::builder;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn blocking_client_handles_session_timeout() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let server = runtime.block_on(async {
+            let server = MockServer::start().await;
+            Mock::given(method("GET"))
+                .and(path("/action/panelCondGet"))
+                .respond_with(
+                    ResponseTemplate::new(200).set_body_string("<html>/action/login</html>"),
+                )
+                .up_to_n_times(1)
+                .mount(&server)
+                .await;
+            Mock::given(method("GET"))
+                .and(path("/action/panelCondGet"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                    "forms": { "pcondform1": { "mode": 1 } }
+                })))
+                .mount(&server)
+                .await;
+            server
+        });
+
+        let client = builder(&server).build_blocking().unwrap();
+        let modes = client.get_status().unwrap();
+        assert_eq!(modes.mode(Area::Area1), Some(Mode::Armed));
+    }
+}
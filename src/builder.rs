@@ -0,0 +1,532 @@
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::client::{Client, ModeChecks};
+use crate::errors::{Error, Result};
+use crate::password::Password;
+use crate::recording::{Recorder, Replay, Transport};
+use crate::retry::RetryPolicy;
+use crate::tls::{Configure, Fingerprint, HttpClient, Pin};
+
+/// The URL scheme used to talk to the panel
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Scheme {
+    /// Plain HTTP
+    Http,
+
+    /// HTTP over TLS
+    #[default]
+    Https,
+}
+
+impl Scheme {
+    fn as_str(self) -> &'static str {
+        match self {
+            Scheme::Http => "http",
+            Scheme::Https => "https",
+        }
+    }
+}
+
+enum PinConfig {
+    Fingerprint(Fingerprint),
+    Pem(Vec<u8>),
+    TrustOnFirstUse(Option<Fingerprint>),
+}
+
+enum TransportConfig {
+    Http,
+    Record(PathBuf),
+    Replay(PathBuf),
+}
+
+enum Target {
+    Unset,
+    Host(String),
+    Ip(IpAddr),
+    Url(String),
+}
+
+/// A builder to configure and construct a [`Client`].
+///
+/// The panel can be addressed by hostname, IP address, socket address or a
+/// full base URL (e.g. when it sits behind a reverse proxy):
+///
+/// ```rust
+/// use std::time::Duration;
+///
+/// let client = alarmate::Client::builder("admin", "changeme")
+///     .host("lupusec.local")
+///     .port(8443)
+///     .connect_timeout(Duration::from_secs(5))
+///     .timeout(Duration::from_secs(30))
+///     .build()
+///     .unwrap();
+/// ```
+#[must_use]
+pub struct ClientBuilder {
+    username: String,
+    password: Password,
+    target: Target,
+    scheme: Scheme,
+    port: Option<u16>,
+    connect_timeout: Option<Duration>,
+    timeout: Option<Duration>,
+    user_agent: Option<String>,
+    proxies: Vec<reqwest::Proxy>,
+    no_proxy: bool,
+    accept_invalid_certs: bool,
+    pin: Option<PinConfig>,
+    retry_policy: RetryPolicy,
+    mode_checks: ModeChecks,
+    transport: TransportConfig,
+}
+
+impl ClientBuilder {
+    pub(crate) fn new(username: &str, password: Password) -> ClientBuilder {
+        ClientBuilder {
+            username: username.into(),
+            password,
+            target: Target::Unset,
+            scheme: Scheme::default(),
+            port: None,
+            connect_timeout: None,
+            timeout: None,
+            user_agent: None,
+            proxies: Vec::new(),
+            no_proxy: false,
+            accept_invalid_certs: true,
+            pin: None,
+            retry_policy: RetryPolicy::none(),
+            mode_checks: ModeChecks::default(),
+            transport: TransportConfig::Http,
+        }
+    }
+
+    /// Address the panel by hostname or IP literal (IPv6 with or without
+    /// brackets).
+    ///
+    /// The host must not include a port or path; [`build`](Self::build)
+    /// fails otherwise. Use [`port`](Self::port) or
+    /// [`base_url`](Self::base_url) instead.
+    pub fn host(mut self, host: impl Into<String>) -> ClientBuilder {
+        let host = host.into();
+        self.target = match host.parse::<IpAddr>() {
+            Ok(ip) => Target::Ip(ip),
+            Err(_) => Target::Host(host),
+        };
+        self
+    }
+
+    /// Address the panel by IP address.
+    pub fn ip_address(mut self, ip_address: impl Into<IpAddr>) -> ClientBuilder {
+        self.target = Target::Ip(ip_address.into());
+        self
+    }
+
+    /// Address the panel by IP address and port.
+    pub fn socket_addr(mut self, addr: impl Into<SocketAddr>) -> ClientBuilder {
+        let addr = addr.into();
+        self.target = Target::Ip(addr.ip());
+        self.port = Some(addr.port());
+        self
+    }
+
+    /// Address the panel by a full base URL, e.g.
+    /// `https://proxy.example.com/lupusec/action/`.
+    ///
+    /// The URL must point to the panel's `action` directory and use the
+    /// `http` or `https` scheme, otherwise [`build`](Self::build) fails. It
+    /// takes precedence over [`scheme`](Self::scheme) and
+    /// [`port`](Self::port).
+    pub fn base_url(mut self, url: impl Into<String>) -> ClientBuilder {
+        self.target = Target::Url(url.into());
+        self
+    }
+
+    /// Set the URL scheme. Defaults to [`Scheme::Https`].
+    pub fn scheme(mut self, scheme: Scheme) -> ClientBuilder {
+        self.scheme = scheme;
+        self
+    }
+
+    /// Set a non-default port.
+    pub fn port(mut self, port: u16) -> ClientBuilder {
+        self.port = Some(port);
+        self
+    }
+
+    /// Set a timeout for establishing a connection to the panel.
+    pub fn connect_timeout(mut self, timeout: Duration) -> ClientBuilder {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Set a timeout for each request, from connecting until the response
+    /// body has been read.
+    pub fn timeout(mut self, timeout: Duration) -> ClientBuilder {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set the `User-Agent` header sent with every request.
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> ClientBuilder {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Route requests through the given proxy. Can be called multiple times.
+    pub fn proxy(mut self, proxy: reqwest::Proxy) -> ClientBuilder {
+        self.proxies.push(proxy);
+        self
+    }
+
+    /// Ignore proxies configured via environment variables.
+    pub fn no_proxy(mut self) -> ClientBuilder {
+        self.no_proxy = true;
+        self
+    }
+
+    /// Whether to accept invalid TLS certificates. Defaults to `true`
+    /// because LUPUSEC panels ship with self-signed certs.
+    pub fn accept_invalid_certs(mut self, accept: bool) -> ClientBuilder {
+        self.accept_invalid_certs = accept;
+        self
+    }
+
+    /// Retry failed requests according to the given policy. Defaults to
+    /// [`RetryPolicy::none`].
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> ClientBuilder {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// After [`Client::change_mode`] was accepted, poll the panel until the
+    /// area reports the requested mode, failing with
+    /// [`Error::ModeNotReached`](crate::Error::ModeNotReached) if it does not
+    /// within `timeout`. Off by default.
+    pub fn verify_mode_change(mut self, timeout: Duration) -> ClientBuilder {
+        self.mode_checks.verify_timeout = Some(timeout);
+        self
+    }
+
+    /// Make [`Client::change_mode`] refuse to switch an area to any mode but
+    /// [`Mode::Disarmed`](crate::Mode::Disarmed) while its
+    /// [`arm_readiness`](Client::arm_readiness) reports blockers, failing
+    /// with [`Error::NotReady`](crate::Error::NotReady).
+    /// [`Client::force_change_mode`] skips the check. Off by default.
+    pub fn guard_arming(mut self, guard: bool) -> ClientBuilder {
+        self.mode_checks.guard_arming = guard;
+        self
+    }
+
+    /// Append every request to the panel and its response to the fixture
+    /// file at `path`, one JSON object per line, e.g. to attach real
+    /// firmware output to a bug report.
+    ///
+    /// The fixture holds the action, form fields, status and body of each
+    /// exchange. Credentials are not recorded and session tokens are
+    /// replaced with `REDACTED`; device names and sensor IDs are kept.
+    pub fn record(mut self, path: impl Into<PathBuf>) -> ClientBuilder {
+        self.transport = TransportConfig::Record(path.into());
+        self
+    }
+
+    /// Answer requests with the responses recorded in the fixture file at
+    /// `path` (see [`record`](ClientBuilder::record)) instead of contacting
+    /// the panel.
+    ///
+    /// Each request gets the next recorded response to the same action and
+    /// form fields; the last one is repeated once all have been used. A
+    /// request without a recorded response fails with
+    /// [`Error::Io`](crate::Error::Io). No host needs to be configured.
+    pub fn replay(mut self, path: impl Into<PathBuf>) -> ClientBuilder {
+        self.transport = TransportConfig::Replay(path.into());
+        self
+    }
+
+    /// Only accept the panel certificate with the given SHA-256 fingerprint.
+    ///
+    /// The certificate is checked during the TLS handshake of every
+    /// connection, before credentials are sent, and hostnames are not
+    /// checked, so this works with the panel's self-signed certificate. A
+    /// mismatch fails with [`Error::CertificateMismatch`](crate::Error::CertificateMismatch).
+    ///
+    /// With the `native-tls` backend the certificate is fetched once with an
+    /// unauthenticated request and then trusted as the only root: it must
+    /// not have expired, and if it changes later the handshake fails with
+    /// [`Error::Http`](crate::Error::Http).
+    pub fn pin_certificate(mut self, fingerprint: Fingerprint) -> ClientBuilder {
+        self.pin = Some(PinConfig::Fingerprint(fingerprint));
+        self
+    }
+
+    /// Only accept the panel certificate contained in the given PEM document.
+    ///
+    /// See [`pin_certificate`](Self::pin_certificate).
+    pub fn pin_certificate_pem(mut self, pem: impl Into<Vec<u8>>) -> ClientBuilder {
+        self.pin = Some(PinConfig::Pem(pem.into()));
+        self
+    }
+
+    /// Trust the first certificate the panel presents and refuse any other
+    /// afterwards.
+    ///
+    /// Pass a previously recorded fingerprint to resume pinning across runs.
+    /// The recorded fingerprint is available via
+    /// [`Client::certificate_fingerprint`] so it can be persisted.
+    pub fn trust_on_first_use(mut self, known: Option<Fingerprint>) -> ClientBuilder {
+        self.pin = Some(PinConfig::TrustOnFirstUse(known));
+        self
+    }
+
+    /// Build the [`Client`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no host was configured, the host or base URL is
+    /// malformed, the host includes a port, the base URL is neither `http`
+    /// nor `https`, the pinned certificate is invalid, or the underlying HTTP
+    /// client cannot be built.
+    pub fn build(mut self) -> Result<Client> {
+        let base_url = match (&self.transport, &self.target) {
+            (TransportConfig::Replay(_), Target::Unset) => {
+                reqwest::Url::parse("http://replay.invalid/action/")?
+            }
+            _ => self.base_url_parsed()?,
+        };
+
+        let pin = match self.pin.take() {
+            None => None,
+            Some(PinConfig::Fingerprint(fingerprint)) => Some(Pin::Fixed(fingerprint)),
+            Some(PinConfig::Pem(pem)) => Some(Pin::Fixed(Fingerprint::from_pem(&pem)?)),
+            Some(PinConfig::TrustOnFirstUse(known)) => {
+                Some(Pin::TrustOnFirstUse(Mutex::new(known)))
+            }
+        };
+
+        let pin = pin.map(Arc::new);
+        let http = || {
+            HttpClient::new(
+                self.configure(),
+                self.accept_invalid_certs,
+                pin.clone(),
+                &base_url,
+            )
+        };
+        let transport = match &self.transport {
+            TransportConfig::Http => Transport::Http(http()?),
+            TransportConfig::Record(path) => Transport::Record(http()?, Recorder::create(path)?),
+            TransportConfig::Replay(path) => Transport::Replay(Replay::load(path)?),
+        };
+
+        Ok(Client::from_parts(
+            transport,
+            self.username,
+            self.password,
+            base_url,
+            pin,
+            self.retry_policy,
+            self.mode_checks,
+        ))
+    }
+
+    /// Apply the timeouts, user agent and proxies to new `reqwest` builders.
+    fn configure(&self) -> Configure {
+        let connect_timeout = self.connect_timeout;
+        let timeout = self.timeout;
+        let user_agent = self.user_agent.clone();
+        let no_proxy = self.no_proxy;
+        let proxies = self.proxies.clone();
+
+        Box::new(move || {
+            let mut builder = reqwest::Client::builder();
+            if let Some(timeout) = connect_timeout {
+                builder = builder.connect_timeout(timeout);
+            }
+            if let Some(timeout) = timeout {
+                builder = builder.timeout(timeout);
+            }
+            if let Some(user_agent) = &user_agent {
+                builder = builder.user_agent(user_agent);
+            }
+            if no_proxy {
+                builder = builder.no_proxy();
+            }
+            for proxy in &proxies {
+                builder = builder.proxy(proxy.clone());
+            }
+            builder
+        })
+    }
+
+    fn base_url_parsed(&self) -> Result<reqwest::Url> {
+        let host = match &self.target {
+            Target::Unset => return Err(url::ParseError::EmptyHost.into()),
+            Target::Url(url) => {
+                let url = reqwest::Url::parse(url)?;
+                if !matches!(url.scheme(), "http" | "https") {
+                    return Err(Error::InvalidArgument(format!(
+                        "unsupported scheme {:?} in base URL, expected http or https",
+                        url.scheme()
+                    )));
+                }
+                return Ok(with_trailing_slash(url));
+            }
+            Target::Ip(IpAddr::V6(ip)) => format!("[{ip}]"),
+            Target::Ip(IpAddr::V4(ip)) => ip.to_string(),
+            Target::Host(host) => {
+                // `[fe80::1]:8443` or `panel:8443`; IPv6 literals without
+                // brackets were parsed by `host()` already
+                let port = host.rsplit_once(':').map(|(_, port)| port);
+                if port.is_some_and(|port| port.parse::<u16>().is_ok()) {
+                    return Err(Error::InvalidArgument(format!(
+                        "host {host:?} includes a port, set it with port() or pass a base \
+                         URL such as \"https://{host}/action/\" to base_url()"
+                    )));
+                }
+                url::Host::parse(host)?.to_string()
+            }
+        };
+
+        let port = self.port.map(|p| format!(":{p}")).unwrap_or_default();
+        let url = format!("{}://{host}{port}/action/", self.scheme.as_str());
+
+        Ok(reqwest::Url::parse(&url)?)
+    }
+}
+
+impl fmt::Debug for ClientBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClientBuilder")
+            .field("username", &self.username)
+            .field("password", &self.password)
+            .field("scheme", &self.scheme)
+            .field("port", &self.port)
+            .field("retry_policy", &self.retry_policy)
+            .field("mode_checks", &self.mode_checks)
+            .finish_non_exhaustive()
+    }
+}
+
+fn with_trailing_slash(mut url: reqwest::Url) -> reqwest::Url {
+    if !url.path().ends_with('/') {
+        let path = format!("{}/", url.path());
+        url.set_path(&path);
+    }
+    url
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base_url(builder: ClientBuilder) -> String {
+        builder.base_url_parsed().unwrap().to_string()
+    }
+
+    fn builder() -> ClientBuilder {
+        ClientBuilder::new("user", "pass".into())
+    }
+
+    #[test]
+    fn hostname_with_port() {
+        let url = base_url(builder().host("lupusec.local").port(8443));
+        assert_eq!(url, "https://lupusec.local:8443/action/");
+    }
+
+    #[test]
+    fn ipv6_host() {
+        assert_eq!(
+            base_url(builder().host("fe80::1")),
+            "https://[fe80::1]/action/"
+        );
+        assert_eq!(
+            base_url(builder().host("[fe80::1]")),
+            "https://[fe80::1]/action/"
+        );
+    }
+
+    #[test]
+    fn socket_addr_and_http_scheme() {
+        let addr: SocketAddr = "10.0.0.2:8080".parse().unwrap();
+        let url = base_url(builder().socket_addr(addr).scheme(Scheme::Http));
+        assert_eq!(url, "http://10.0.0.2:8080/action/");
+    }
+
+    #[test]
+    fn full_base_url_gets_trailing_slash() {
+        let url = base_url(builder().base_url("https://proxy.example.com/lupusec/action"));
+        assert_eq!(url, "https://proxy.example.com/lupusec/action/");
+    }
+
+    #[test]
+    fn missing_host_fails() {
+        assert!(matches!(
+            builder().build(),
+            Err(crate::Error::InvalidUrl(url::ParseError::EmptyHost))
+        ));
+    }
+
+    #[test]
+    fn host_with_path_fails() {
+        assert!(builder().host("panel/evil").build().is_err());
+        assert!(builder().host("panel\\evil").build().is_err());
+        assert!(builder().host("user@panel").build().is_err());
+    }
+
+    #[test]
+    fn host_with_port_fails() {
+        for host in ["panel:8443", "[fe80::1]:8443", "10.0.0.2:80"] {
+            assert!(
+                matches!(
+                    builder().host(host).build(),
+                    Err(crate::Error::InvalidArgument(message)) if message.contains("base_url")
+                ),
+                "{host}"
+            );
+        }
+    }
+
+    #[test]
+    fn international_host_is_encoded() {
+        assert_eq!(
+            base_url(builder().host("Alarm.Bücher.example")),
+            "https://alarm.xn--bcher-kva.example/action/"
+        );
+    }
+
+    #[test]
+    fn invalid_pem_pin_fails() {
+        let result = builder()
+            .host("panel")
+            .pin_certificate_pem("garbage")
+            .build();
+        assert!(matches!(result, Err(crate::Error::InvalidCertificate(_))));
+    }
+
+    #[test]
+    fn invalid_base_url_fails() {
+        assert!(builder().base_url("not a url").build().is_err());
+    }
+
+    #[test]
+    fn base_url_with_other_scheme_fails() {
+        for url in [
+            "ftp://panel/action/",
+            "file:///action/",
+            "mailto:panel@example.com",
+        ] {
+            assert!(
+                matches!(
+                    builder().base_url(url).build(),
+                    Err(crate::Error::InvalidArgument(_))
+                ),
+                "{url}"
+            );
+        }
+    }
+}
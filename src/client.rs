@@ -1,118 +1,537 @@
 use reqwest::header;
 use serde::Serialize;
 
+use tokio::sync::Mutex;
+
+use futures::Stream;
+
+use std::fmt;
+use std::future::Future;
 use std::net::Ipv4Addr;
+use std::sync::Arc;
+use std::time::Duration;
 
 use crate::Modes;
-use crate::constants::{Area, Mode};
+use crate::builder::ClientBuilder;
+use crate::constants::{Area, Mode, ThermostatMode};
 use crate::errors::{Error, Result};
-use crate::resources::{ApiResponse, devices, panel, response};
+use crate::password::Password;
+use crate::readiness::ArmReadiness;
+use crate::recording::{self, Reply, Transport};
+use crate::resources::{
+    ApiResponse, KindList, devices, dimmers, history, panel, response, shutters, switches,
+    thermostats, validate_percent,
+};
+use crate::retry::RetryPolicy;
+use crate::tls::{Fingerprint, Pin};
+use crate::watch::{self, Event};
 
 /// Holds the credentials and a session token
+///
+/// The client is cheap to clone and can be shared between tasks: clones use
+/// the same connection pool and session token.
+#[derive(Clone)]
 pub struct Client {
-    client: reqwest::Client,
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    transport: Transport,
     username: String,
-    password: String,
+    password: Password,
     base_url: reqwest::Url,
-    token: Option<String>,
+    token: Mutex<Option<String>>,
+    pin: Option<Arc<Pin>>,
+    retry_policy: RetryPolicy,
+    mode_checks: ModeChecks,
+}
+
+/// How often [`Client::change_mode`] polls the panel while waiting for the
+/// requested mode
+const MODE_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The checks [`Client::change_mode`] makes around a mode change
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct ModeChecks {
+    /// How long to wait for the panel to report the requested mode
+    pub(crate) verify_timeout: Option<Duration>,
+    /// Whether to refuse arming while the area is not ready
+    pub(crate) guard_arming: bool,
+}
+
+/// Whether repeating a request is harmless.
+#[derive(Clone, Copy)]
+enum Idempotency {
+    Idempotent,
+    NonIdempotent,
 }
 
 impl Client {
     /// Construct a client.
     ///
     /// The client accepts self-signed TLS certificates because LUPUSEC panels
-    /// ship with self-signed certs by default.
+    /// ship with self-signed certs by default. This is a shortcut for
+    /// [`Client::builder`] with only [`ip_address`](ClientBuilder::ip_address)
+    /// set.
     ///
     /// # Errors
     ///
     /// Returns an error if the underlying HTTP client cannot be built (e.g.
     /// due to malformed proxy environment variables).
     pub fn new(username: &str, password: &str, ip_address: Ipv4Addr) -> Result<Client> {
-        let client = reqwest::Client::builder()
-            .danger_accept_invalid_certs(true)
-            .build()?;
+        Client::builder(username, password)
+            .ip_address(ip_address)
+            .build()
+    }
+
+    /// Create a [`ClientBuilder`] to configure the host, scheme, timeouts and
+    /// proxies used to reach the panel.
+    ///
+    /// The password is kept in a [`Password`], which is wiped from memory
+    /// when the client is dropped.
+    pub fn builder(username: &str, password: impl Into<Password>) -> ClientBuilder {
+        ClientBuilder::new(username, password.into())
+    }
 
-        let base_url = format!("https://{ip_address}/action/")
-            .parse()
-            .expect("base URL should be valid for well-formed IPv4");
+    pub(crate) fn from_parts(
+        transport: Transport,
+        username: String,
+        password: Password,
+        base_url: reqwest::Url,
+        pin: Option<Arc<Pin>>,
+        retry_policy: RetryPolicy,
+        mode_checks: ModeChecks,
+    ) -> Client {
+        Client {
+            inner: Arc::new(Inner {
+                transport,
+                username,
+                password,
+                base_url,
+                token: Mutex::new(None),
+                pin,
+                retry_policy,
+                mode_checks,
+            }),
+        }
+    }
 
-        Ok(Client {
-            client,
-            username: username.into(),
-            password: password.into(),
-            base_url,
-            token: None,
-        })
+    /// The fingerprint of the pinned panel certificate.
+    ///
+    /// With [`trust_on_first_use`](ClientBuilder::trust_on_first_use) this is
+    /// `None` until the first request has been made; persist the returned
+    /// value to keep pinning the same certificate across runs.
+    pub fn certificate_fingerprint(&self) -> Option<Fingerprint> {
+        self.inner.pin.as_ref().and_then(|pin| pin.fingerprint())
     }
 
-    /// Get the status of the Alarm Panel.
+    /// Get the status of the Alarm Panel: the mode, alarm and entry/exit
+    /// delay of each area and any fault or alert messages.
     ///
     /// Automatically retries once if the panel reports a session timeout.
-    pub async fn get_status(&mut self) -> Result<Modes> {
+    pub async fn get_status(&self) -> Result<Modes> {
         self.get::<panel::Condition>("panelCondGet").await
     }
 
     /// Change the mode of the given area.
     ///
     /// Automatically retries once if the panel reports a session timeout,
-    /// clearing the cached token before the retry.
-    pub async fn change_mode(&mut self, area: Area, mode: Mode) -> Result {
-        let payload = &[("mode", mode as u8), ("area", area as u8)];
+    /// clearing the cached token before the retry. Other failures are only
+    /// retried if the request never reached the panel.
+    ///
+    /// The panel accepts a mode change before it checks whether the area can
+    /// be armed. With [`verify_mode_change`](ClientBuilder::verify_mode_change)
+    /// this waits until the panel reports the requested mode and fails with
+    /// [`Error::ModeNotReached`] otherwise. With
+    /// [`guard_arming`](ClientBuilder::guard_arming) it refuses to arm while
+    /// devices of the area are open, faulty or cannot be checked.
+    pub async fn change_mode(&self, area: Area, mode: Mode) -> Result {
+        if self.inner.mode_checks.guard_arming && mode != Mode::Disarmed {
+            let readiness = self.arm_readiness(area).await?;
+            if !readiness.is_ready() {
+                return Err(Error::NotReady(readiness));
+            }
+        }
+
+        self.force_change_mode(area, mode).await
+    }
+
+    /// Change the mode of the given area like [`Client::change_mode`], but
+    /// without the [`guard_arming`](ClientBuilder::guard_arming) check.
+    pub async fn force_change_mode(&self, area: Area, mode: Mode) -> Result {
+        if let Mode::Unknown(_) = mode {
+            return Err(Error::InvalidArgument(format!("unsupported mode {mode:?}")));
+        }
 
-        self.post::<_, response::Response>("panelCondPost", payload)
+        let payload = &[("mode", u8::from(mode)), ("area", u8::from(area))];
+
+        let message = self
+            .post::<_, response::Response>("panelCondPost", payload, Idempotency::NonIdempotent)
             .await?;
 
-        Ok(())
+        match self.inner.mode_checks.verify_timeout {
+            Some(timeout) => self.await_mode(area, mode, timeout, message).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Check which devices of the given area stand in the way of arming it:
+    /// open door contacts, devices reporting a fault, a low battery or
+    /// tampering, and devices whose state cannot be read.
+    ///
+    /// Automatically retries once if the panel reports a session timeout.
+    pub async fn arm_readiness(&self, area: Area) -> Result<ArmReadiness> {
+        let (devices, malformed) = self.get::<devices::Rows>("deviceListGet").await?;
+        Ok(ArmReadiness::from_devices(area, &devices, &malformed))
     }
 
     /// List all devices managed by the alarm panel.
     ///
     /// Automatically retries once if the panel reports a session timeout.
-    pub async fn list_devices(&mut self) -> Result<Vec<devices::Device>> {
+    pub async fn list_devices(&self) -> Result<Vec<devices::Device>> {
         self.get::<devices::List>("deviceListGet").await
     }
 
+    /// List all power switches and smart plugs with their on/off state and,
+    /// for metered switches, power and energy readings.
+    ///
+    /// Automatically retries once if the panel reports a session timeout.
+    pub async fn list_switches(&self) -> Result<Vec<switches::Switch>> {
+        self.get::<KindList<switches::Switch>>("deviceListGet")
+            .await
+    }
+
+    /// Get the state of the power switch or smart plug with the given sensor
+    /// ID.
+    ///
+    /// Returns [`Error::DeviceNotFound`] if there is no such switch.
+    pub async fn switch(&self, sid: &str) -> Result<switches::Switch> {
+        self.list_switches()
+            .await?
+            .into_iter()
+            .find(|switch| switch.sid == sid)
+            .ok_or_else(|| Error::DeviceNotFound(sid.into()))
+    }
+
+    /// Turn the power switch or smart plug with the given sensor ID on or
+    /// off.
+    ///
+    /// Automatically retries once if the panel reports a session timeout,
+    /// clearing the cached token before the retry.
+    pub async fn set_switch(&self, sid: &str, on: bool) -> Result {
+        let payload = &[("id", sid), ("switch", if on { "1" } else { "0" })];
+
+        self.post::<_, response::Response>("deviceSwitchPSSPost", payload, Idempotency::Idempotent)
+            .await?;
+
+        Ok(())
+    }
+
+    /// List all thermostats and radiator valves with their measured and
+    /// target temperature, valve position and mode.
+    ///
+    /// Automatically retries once if the panel reports a session timeout.
+    pub async fn list_thermostats(&self) -> Result<Vec<thermostats::Thermostat>> {
+        self.get::<KindList<thermostats::Thermostat>>("deviceListGet")
+            .await
+    }
+
+    /// Get the state of the thermostat with the given sensor ID.
+    ///
+    /// Returns [`Error::DeviceNotFound`] if there is no such thermostat.
+    pub async fn thermostat(&self, sid: &str) -> Result<thermostats::Thermostat> {
+        self.list_thermostats()
+            .await?
+            .into_iter()
+            .find(|thermostat| thermostat.sid == sid)
+            .ok_or_else(|| Error::DeviceNotFound(sid.into()))
+    }
+
+    /// Set the target temperature (in °C) of the thermostat with the given
+    /// sensor ID.
+    ///
+    /// Returns [`Error::InvalidArgument`] without contacting the panel if the
+    /// temperature is not a multiple of 0.5 °C between
+    /// [`Thermostat::MIN_TARGET_TEMPERATURE`](thermostats::Thermostat::MIN_TARGET_TEMPERATURE)
+    /// and [`Thermostat::MAX_TARGET_TEMPERATURE`](thermostats::Thermostat::MAX_TARGET_TEMPERATURE).
+    pub async fn set_target_temperature(&self, sid: &str, celsius: f64) -> Result {
+        let celsius = thermostats::validate_target_temperature(celsius)?.to_string();
+        let payload = &[("id", sid), ("set_temp", &celsius)];
+
+        self.post::<_, response::Response>(
+            "deviceThermostatPost",
+            payload,
+            Idempotency::Idempotent,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Set the operating mode of the thermostat with the given sensor ID.
+    pub async fn set_thermostat_mode(&self, sid: &str, mode: ThermostatMode) -> Result {
+        if let ThermostatMode::Unknown(_) = mode {
+            return Err(Error::InvalidArgument(format!(
+                "unsupported thermostat mode {mode:?}"
+            )));
+        }
+
+        let mode = u8::from(mode).to_string();
+        let payload = &[("id", sid), ("thermo_mode", &mode)];
+
+        self.post::<_, response::Response>(
+            "deviceThermostatPost",
+            payload,
+            Idempotency::Idempotent,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// List all shutters and awnings with their current position.
+    ///
+    /// Automatically retries once if the panel reports a session timeout.
+    pub async fn list_shutters(&self) -> Result<Vec<shutters::Shutter>> {
+        self.get::<KindList<shutters::Shutter>>("deviceListGet")
+            .await
+    }
+
+    /// Open, close, stop or position the shutter or awning with the given
+    /// sensor ID.
+    ///
+    /// Returns [`Error::InvalidArgument`] without contacting the panel if a
+    /// position above 100 % is requested.
+    pub async fn move_shutter(&self, sid: &str, command: shutters::ShutterCommand) -> Result {
+        let (key, value) = command.to_form()?;
+        let payload = &[("id", sid), (key, &value)];
+
+        self.post::<_, response::Response>("deviceShutterPost", payload, Idempotency::Idempotent)
+            .await?;
+
+        Ok(())
+    }
+
+    /// List all dimmers with their current brightness.
+    ///
+    /// Automatically retries once if the panel reports a session timeout.
+    pub async fn list_dimmers(&self) -> Result<Vec<dimmers::Dimmer>> {
+        self.get::<KindList<dimmers::Dimmer>>("deviceListGet").await
+    }
+
+    /// Set the brightness (in percent, 0 = off) of the dimmer with the given
+    /// sensor ID.
+    ///
+    /// Returns [`Error::InvalidArgument`] without contacting the panel if the
+    /// level is above 100.
+    pub async fn set_dimmer_level(&self, sid: &str, level: u8) -> Result {
+        let level = validate_percent(level)?.to_string();
+        let payload = &[("id", sid), ("level", &level)];
+
+        self.post::<_, response::Response>("deviceDimmerPost", payload, Idempotency::Idempotent)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Poll the panel every `interval` and yield an [`Event`] for every
+    /// change of an area's mode or a device's state, battery, tamper or
+    /// condition status, and for added or removed devices.
+    ///
+    /// The first poll only records the initial state. Failed polls are
+    /// yielded as errors and the stream keeps going, doubling the interval
+    /// for every consecutive failure (up to five minutes) until the panel is
+    /// reachable again.
+    ///
+    /// ```no_run
+    /// use futures::StreamExt;
+    /// use std::time::Duration;
+    ///
+    /// # async fn run(client: alarmate::Client) {
+    /// let mut events = std::pin::pin!(client.watch(Duration::from_secs(5)));
+    /// while let Some(event) = events.next().await {
+    ///     println!("{event:?}");
+    /// }
+    /// # }
+    /// ```
+    pub fn watch(&self, interval: Duration) -> impl Stream<Item = Result<Event>> + Send + use<> {
+        watch::watch(self.clone(), interval)
+    }
+
+    /// Read the panel's event log, keeping only the entries that pass the
+    /// filter. Entries that cannot be parsed are skipped.
+    ///
+    /// The layout of the log and the [`EventKind`](crate::EventKind) codes
+    /// have not been verified against a real panel, see
+    /// [`HistoryEntry`](history::HistoryEntry).
+    ///
+    /// Automatically retries once if the panel reports a session timeout.
+    pub async fn history(
+        &self,
+        filter: &history::HistoryFilter,
+    ) -> Result<Vec<history::HistoryEntry>> {
+        let mut entries = self.get::<history::History>("historyGet").await?;
+        entries.retain(|entry| filter.matches(entry));
+        Ok(entries)
+    }
+
+    /// Poll the panel until `area` is in `mode` or `timeout` expires.
+    async fn await_mode(
+        &self,
+        area: Area,
+        mode: Mode,
+        timeout: Duration,
+        message: String,
+    ) -> Result {
+        let deadline = tokio::time::Instant::now() + timeout;
+
+        loop {
+            let modes = self.get_status().await?;
+            let actual = modes.mode(area);
+            if actual == Some(mode) {
+                return Ok(());
+            }
+
+            let now = tokio::time::Instant::now();
+            if now >= deadline {
+                let alerts: Vec<_> = modes.alerts.into_values().collect();
+                let reason = if alerts.is_empty() {
+                    Some(message).filter(|message| !message.trim().is_empty())
+                } else {
+                    Some(alerts.join("; "))
+                };
+
+                return Err(Error::ModeNotReached {
+                    area,
+                    requested: mode,
+                    actual,
+                    reason,
+                });
+            }
+
+            tokio::time::sleep(MODE_POLL_INTERVAL.min(deadline - now)).await;
+        }
+    }
+
     fn url(&self, path: &str) -> reqwest::Url {
-        self.base_url
+        self.inner
+            .base_url
             .join(path)
             .expect("action path should be a valid relative URL segment")
     }
 
-    async fn get<T>(&mut self, action: &str) -> Result<T::Type>
+    /// Run `request` until it succeeds or the retry policy gives up.
+    async fn with_retry<F, Fut, R>(&self, idempotency: Idempotency, mut request: F) -> Result<R>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<R>>,
+    {
+        let policy = &self.inner.retry_policy;
+        let idempotent = matches!(idempotency, Idempotency::Idempotent);
+        let mut attempt = 1;
+
+        loop {
+            match request().await {
+                Err(e) if policy.should_retry(attempt, &e, idempotent) => {
+                    let backoff = policy.backoff(attempt);
+                    trace_event!(info, attempt, error = %e, ?backoff, "retrying panel request");
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
+                }
+                Err(e) => {
+                    trace_event!(warn, attempt, error = %e, "panel request failed");
+                    return Err(e);
+                }
+                other => return other,
+            }
+        }
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(action = %action))
+    )]
+    async fn get<T>(&self, action: &str) -> Result<T::Type>
     where
         T: ApiResponse + serde::de::DeserializeOwned,
     {
-        let res = self.send_get(action).await?;
-        match parse_and_convert::<T>(res).await {
-            Err(ref e) if e.is_session_timeout() => {}
+        self.with_retry(Idempotency::Idempotent, || self.get_once::<T>(action))
+            .await
+    }
+
+    async fn get_once<T>(&self, action: &str) -> Result<T::Type>
+    where
+        T: ApiResponse + serde::de::DeserializeOwned,
+    {
+        let reply = self.send_get(action).await?;
+        match parse_and_convert::<T>(reply) {
+            Err(ref e) if e.is_session_timeout() => {
+                trace_event!(debug, "session expired, repeating request");
+            }
             other => return other,
         }
-        parse_and_convert::<T>(self.send_get(action).await?).await
+        parse_and_convert::<T>(self.send_get(action).await?)
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(action = %action))
+    )]
+    async fn post<T, D>(&self, action: &str, form: &T, idempotency: Idempotency) -> Result<D::Type>
+    where
+        T: Serialize,
+        D: ApiResponse + serde::de::DeserializeOwned,
+    {
+        self.with_retry(idempotency, || self.post_once::<T, D>(action, form))
+            .await
     }
 
-    async fn post<T, D>(&mut self, action: &str, form: &T) -> Result<D::Type>
+    async fn post_once<T, D>(&self, action: &str, form: &T) -> Result<D::Type>
     where
         T: Serialize,
         D: ApiResponse + serde::de::DeserializeOwned,
     {
         let token = self.get_or_fetch_token().await?;
-        let res = self.send_post(action, form, &token).await?;
-        match parse_and_convert::<D>(res).await {
-            Err(ref e) if e.is_session_timeout() => {}
+        let reply = self.send_post(action, form, &token).await?;
+        match parse_and_convert::<D>(reply) {
+            Err(ref e) if e.is_session_timeout() => {
+                trace_event!(debug, "session expired, refreshing token");
+            }
             other => return other,
         }
-        self.token = None;
+        self.invalidate_token(&token).await;
         let token = self.get_or_fetch_token().await?;
-        parse_and_convert::<D>(self.send_post(action, form, &token).await?).await
+        parse_and_convert::<D>(self.send_post(action, form, &token).await?)
     }
 
-    async fn send_get(&self, action: &str) -> Result<reqwest::Response> {
-        Ok(self
-            .client
+    async fn send_get(&self, action: &str) -> Result<Reply> {
+        let http = match &self.inner.transport {
+            Transport::Replay(replay) => return replay.reply("GET", action, None),
+            Transport::Http(http) | Transport::Record(http, _) => http,
+        };
+
+        let client = http.get().await?;
+        #[cfg(feature = "tracing")]
+        let started = std::time::Instant::now();
+        let res = client
             .get(self.url(action))
-            .basic_auth(&self.username, Some(&self.password))
+            .basic_auth(&self.inner.username, Some(self.inner.password.expose()))
             .send()
-            .await?)
+            .await
+            .map_err(|e| http.error(e))?;
+        let reply = Reply::read(res).await?;
+        trace_event!(
+            debug,
+            method = "GET",
+            status = reply.status.as_u16(),
+            latency = ?started.elapsed(),
+            "panel responded"
+        );
+
+        if let Transport::Record(_, recorder) = &self.inner.transport {
+            recorder.record("GET", action, None, &reply)?;
+        }
+        Ok(reply)
     }
 
     async fn send_post<T: Serialize + ?Sized>(
@@ -120,60 +539,89 @@ impl Client {
         action: &str,
         form: &T,
         token: &str,
-    ) -> Result<reqwest::Response> {
-        Ok(self
-            .client
+    ) -> Result<Reply> {
+        let http = match &self.inner.transport {
+            Transport::Replay(replay) => {
+                return replay.reply("POST", action, Some(recording::form_fields(form)?));
+            }
+            Transport::Http(http) | Transport::Record(http, _) => http,
+        };
+
+        let client = http.get().await?;
+        #[cfg(feature = "tracing")]
+        let started = std::time::Instant::now();
+        let res = client
             .post(self.url(action))
             .form(form)
-            .basic_auth(&self.username, Some(&self.password))
+            .basic_auth(&self.inner.username, Some(self.inner.password.expose()))
             .header("x-token", header::HeaderValue::from_str(token)?)
             .send()
-            .await?)
+            .await
+            .map_err(|e| http.error(e))?;
+        let reply = Reply::read(res).await?;
+        trace_event!(
+            debug,
+            method = "POST",
+            status = reply.status.as_u16(),
+            latency = ?started.elapsed(),
+            "panel responded"
+        );
+
+        if let Transport::Record(_, recorder) = &self.inner.transport {
+            recorder.record("POST", action, Some(recording::form_fields(form)?), &reply)?;
+        }
+        Ok(reply)
     }
 
     /// Fetch the cached token or request a new one from the panel.
-    async fn get_or_fetch_token(&mut self) -> Result<String> {
-        if let Some(token) = &self.token {
+    ///
+    /// The lock is held while fetching so that concurrent callers wait for a
+    /// single `tokenGet` request instead of each issuing their own.
+    ///
+    /// The token itself is never logged.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    async fn get_or_fetch_token(&self) -> Result<String> {
+        let mut cached = self.inner.token.lock().await;
+        if let Some(token) = &*cached {
+            trace_event!(trace, "using cached token");
             return Ok(token.clone());
         }
 
         let token = self.get_token().await?;
-        self.token = Some(token.clone());
+        trace_event!(debug, "fetched a new token");
+        *cached = Some(token.clone());
         Ok(token)
     }
 
-    async fn get_token(&mut self) -> Result<String> {
-        self.get::<response::Response>("tokenGet").await
+    /// Clear the cached token unless another caller already replaced it.
+    async fn invalidate_token(&self, stale: &str) {
+        let mut cached = self.inner.token.lock().await;
+        if cached.as_deref() == Some(stale) {
+            *cached = None;
+        }
     }
 
-    #[cfg(test)]
-    fn with_base_url(username: &str, password: &str, base_url: reqwest::Url) -> Result<Client> {
-        let client = reqwest::Client::builder().build()?;
-
-        Ok(Client {
-            client,
-            username: username.into(),
-            password: password.into(),
-            base_url,
-            token: None,
-        })
+    async fn get_token(&self) -> Result<String> {
+        self.get::<response::Response>("tokenGet").await
     }
 }
 
-async fn parse_response<D>(res: reqwest::Response) -> Result<D>
-where
-    D: ApiResponse + serde::de::DeserializeOwned,
-{
-    let status = res.status();
-    let body = res.text().await?;
-    parse_body(status, &body)
+impl fmt::Debug for Client {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Client")
+            .field("username", &self.inner.username)
+            .field("password", &self.inner.password)
+            .field("base_url", &self.inner.base_url.as_str())
+            .field("retry_policy", &self.inner.retry_policy)
+            .finish_non_exhaustive()
+    }
 }
 
-async fn parse_and_convert<D>(res: reqwest::Response) -> Result<D::Type>
+fn parse_and_convert<D>(reply: Reply) -> Result<D::Type>
 where
     D: ApiResponse + serde::de::DeserializeOwned,
 {
-    parse_response::<D>(res).await?.into_result()
+    parse_body::<D>(reply.status, &reply.body)?.into_result()
 }
 
 fn parse_body<D>(status: reqwest::StatusCode, body: &str) -> Result<D>
@@ -181,6 +629,11 @@ where
     D: ApiResponse + serde::de::DeserializeOwned,
 {
     if !status.is_success() {
+        trace_event!(
+            debug,
+            status = status.as_u16(),
+            "panel returned an error status"
+        );
         let error = if status == reqwest::StatusCode::UNAUTHORIZED {
             Error::Unauthorized
         } else {
@@ -197,18 +650,49 @@ where
     // returning an HTML page instead of JSON. Detect this before reporting
     // the serde error so callers can retry with a fresh session.
     match serde_json::from_str(&body.replace('\t', "")) {
-        Err(_) if body.contains("/action/login") => Err(Error::SessionTimeout),
-        Err(e) => Err(e.into()),
+        Err(_) if body.contains("/action/login") => {
+            trace_event!(debug, "panel returned the login page");
+            Err(Error::SessionTimeout)
+        }
+        Err(e) => {
+            // The body is not logged since it may contain a token.
+            trace_event!(
+                warn,
+                category = ?e.classify(),
+                line = e.line(),
+                column = e.column(),
+                "cannot parse panel response"
+            );
+            Err(e.into())
+        }
         Ok(model) => Ok(model),
     }
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
-    use wiremock::matchers::{method, path};
+    use crate::readiness::{Blocker, Problem};
+    use wiremock::matchers::{body_string, header, method, path};
     use wiremock::{Mock, MockServer, ResponseTemplate};
 
+    /// Mount a `tokenGet` mock that hands out the token `tok123`.
+    pub(crate) async fn mount_token(server: &MockServer) {
+        Mock::given(method("GET"))
+            .and(path("/action/tokenGet"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({"result": 1, "message": "tok123"})),
+            )
+            .mount(server)
+            .await;
+    }
+
+    /// A client builder that talks to the mock `server`.
+    pub(crate) fn builder(server: &MockServer) -> ClientBuilder {
+        Client::builder("user", "pass").base_url(format!("{}/action/", server.uri()))
+    }
+
     #[test]
     fn url_construction() {
         let client = Client::new("user", "pass", "192.168.1.1".parse().unwrap()).unwrap();
@@ -216,6 +700,14 @@ mod tests {
         assert_eq!(url.as_str(), "https://192.168.1.1/action/panelCondGet");
     }
 
+    #[test]
+    fn debug_redacts_password() {
+        let client = Client::new("user", "hunter2", "192.168.1.1".parse().unwrap()).unwrap();
+        let debug = format!("{client:?}");
+        assert!(debug.contains("user"));
+        assert!(!debug.contains("hunter2"));
+    }
+
     #[test]
     fn parse_body_valid_json() {
         let body = r#"{"result": 1, "message": "token123"}"#;
@@ -259,6 +751,534 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn pinned_certificate_rejects_plain_http() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+
+        let client = builder(&server).trust_on_first_use(None).build().unwrap();
+
+        // At most an unauthenticated probe may reach the server.
+        let result = client.get_status().await;
+        assert!(matches!(
+            result.unwrap_err(),
+            Error::CertificateMismatch { actual: None, .. }
+        ));
+        let requests = server.received_requests().await.unwrap();
+        assert!(requests.len() <= 1);
+        assert!(
+            requests
+                .iter()
+                .all(|request| request.headers.get("authorization").is_none())
+        );
+    }
+
+    #[test]
+    fn client_is_send_sync_clone() {
+        fn assert_traits<T: Clone + Send + Sync + 'static>() {}
+        assert_traits::<Client>();
+    }
+
+    #[tokio::test]
+    async fn concurrent_requests_fetch_token_once() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/action/tokenGet"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({"result": 1, "message": "tok123"}))
+                    .set_delay(std::time::Duration::from_millis(50)),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(path("/action/panelCondPost"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({"result": 1, "message": "ok"})),
+            )
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let client = builder(&server).build().unwrap();
+
+        let other = client.clone();
+        let task = tokio::spawn(async move { other.change_mode(Area::Area2, Mode::Armed).await });
+        let result = client.change_mode(Area::Area1, Mode::Armed).await;
+        assert!(result.is_ok());
+        assert!(task.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn concurrent_session_timeouts_refresh_token_once() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/action/tokenGet"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({"result": 1, "message": "expired"})),
+            )
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/action/tokenGet"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({"result": 1, "message": "fresh"})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        // Both callers run into the expired session with the first token
+        Mock::given(method("POST"))
+            .and(path("/action/panelCondPost"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string("<html>/action/login</html>")
+                    .set_delay(std::time::Duration::from_millis(50)),
+            )
+            .up_to_n_times(2)
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(path("/action/panelCondPost"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({"result": 1, "message": "ok"})),
+            )
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let client = builder(&server).build().unwrap();
+
+        let (a, b) = tokio::join!(
+            client.change_mode(Area::Area1, Mode::Armed),
+            client.change_mode(Area::Area2, Mode::Armed),
+        );
+        assert!(a.is_ok() && b.is_ok());
+    }
+
+    #[tokio::test]
+    async fn get_retries_transient_errors() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/action/panelCondGet"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(2)
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/action/panelCondGet"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "forms": {
+                    "pcondform1": { "mode": 1 },
+                    "pcondform2": { "mode": 0 }
+                }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = builder(&server)
+            .retry_policy(RetryPolicy::default().initial_backoff(Duration::from_millis(1)))
+            .build()
+            .unwrap();
+        let modes = client.get_status().await.unwrap();
+        assert_eq!(modes.mode(Area::Area1), Some(Mode::Armed));
+    }
+
+    #[tokio::test]
+    async fn mode_change_is_not_retried_after_reaching_panel() {
+        let server = MockServer::start().await;
+
+        mount_token(&server).await;
+
+        Mock::given(method("POST"))
+            .and(path("/action/panelCondPost"))
+            .respond_with(ResponseTemplate::new(502))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = builder(&server)
+            .retry_policy(RetryPolicy::default().initial_backoff(Duration::from_millis(1)))
+            .build()
+            .unwrap();
+        let result = client.change_mode(Area::Area1, Mode::Armed).await;
+        assert!(matches!(
+            result.unwrap_err(),
+            Error::UnexpectedResponse { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn verified_mode_change_waits_for_mode() {
+        let server = MockServer::start().await;
+        let condition = |area1: u8| {
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "forms": {
+                    "pcondform1": { "mode": area1 },
+                    "pcondform2": { "mode": 0 }
+                },
+                "updates": { "rf_ex": "Door open" }
+            }))
+        };
+
+        mount_token(&server).await;
+        Mock::given(method("POST"))
+            .and(path("/action/panelCondPost"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({"result": 1, "message": ""})),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/action/panelCondGet"))
+            .respond_with(condition(0))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/action/panelCondGet"))
+            .respond_with(condition(1))
+            .mount(&server)
+            .await;
+
+        let client = builder(&server)
+            .verify_mode_change(Duration::from_secs(5))
+            .build()
+            .unwrap();
+        client.change_mode(Area::Area1, Mode::Armed).await.unwrap();
+
+        let client = builder(&server)
+            .verify_mode_change(Duration::from_millis(100))
+            .build()
+            .unwrap();
+        let result = client.change_mode(Area::Area2, Mode::Armed).await;
+        assert!(matches!(
+            result.unwrap_err(),
+            Error::ModeNotReached {
+                area: Area::Area2,
+                requested: Mode::Armed,
+                actual: Some(Mode::Disarmed),
+                reason: Some(reason),
+            } if reason == "Door open"
+        ));
+    }
+
+    #[tokio::test]
+    async fn guarded_arming_refuses_with_open_contacts() {
+        let server = MockServer::start().await;
+
+        mount_token(&server).await;
+        Mock::given(method("GET"))
+            .and(path("/action/deviceListGet"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "senrows": [{
+                    "sid": "RF:01", "type": 4, "name": "Front Door", "area": 1,
+                    "status_ex": 1, "cond_ok": 1, "battery_ok": 1, "tamper_ok": 1
+                }]
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/action/panelCondPost"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({"result": 1, "message": ""})),
+            )
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let client = builder(&server).guard_arming(true).build().unwrap();
+
+        let result = client.change_mode(Area::Area1, Mode::Armed).await;
+        assert!(
+            matches!(result.unwrap_err(), Error::NotReady(readiness) if readiness.blockers.len() == 1)
+        );
+
+        client
+            .change_mode(Area::Area1, Mode::Disarmed)
+            .await
+            .unwrap();
+        client
+            .force_change_mode(Area::Area1, Mode::Armed)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn guarded_arming_blocks_on_malformed_device_rows() {
+        let server = MockServer::start().await;
+
+        mount_token(&server).await;
+        Mock::given(method("GET"))
+            .and(path("/action/deviceListGet"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "senrows": [
+                    {
+                        "sid": "RF:01", "type": 4, "name": "Front Door", "area": 1,
+                        "status_ex": 0, "cond_ok": 1, "battery_ok": 1, "tamper_ok": 1
+                    },
+                    { "sid": "RF:02", "type": 4, "name": "Back Door", "area": 1 }
+                ]
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/action/panelCondPost"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({"result": 1, "message": ""})),
+            )
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let client = builder(&server).guard_arming(true).build().unwrap();
+
+        // The closed front door is fine, but the back door cannot be checked
+        let result = client.change_mode(Area::Area1, Mode::Armed).await;
+        let Error::NotReady(readiness) = result.unwrap_err() else {
+            panic!("expected the arming guard to refuse");
+        };
+        assert_eq!(
+            readiness.blockers,
+            [Blocker {
+                sid: "RF:02".into(),
+                name: "Back Door".into(),
+                kind: Some(crate::DeviceKind::DoorContact),
+                problem: Problem::Unknown,
+            }]
+        );
+
+        // Listing devices still skips the malformed row
+        assert_eq!(client.list_devices().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn history_is_filtered() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/action/historyGet"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "hisrows": [
+                    { "d": "1700000000", "a": "1", "z": "3", "sid": "RF:01", "e": "1", "u": "" },
+                    { "d": "1700000100", "a": "1", "z": "", "sid": "", "e": "3", "u": "admin" },
+                    { "d": "1700000200", "a": "2", "z": "7", "sid": "RF:02", "e": "1", "u": "" }
+                ]
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = builder(&server).build().unwrap();
+        let filter = history::HistoryFilter::default()
+            .since(std::time::UNIX_EPOCH + Duration::from_secs(1_700_000_150))
+            .kind(crate::EventKind::Alarm);
+        let entries = client.history(&filter).await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].sid.as_deref(), Some("RF:02"));
+        assert_eq!(entries[0].area, Area::Area2);
+    }
+
+    #[tokio::test]
+    async fn set_switch_posts_state() {
+        let server = MockServer::start().await;
+
+        mount_token(&server).await;
+
+        Mock::given(method("POST"))
+            .and(path("/action/deviceSwitchPSSPost"))
+            .and(header("x-token", "tok123"))
+            .and(body_string("id=RF%3A02&switch=1"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({"result": 1, "message": "ok"})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = builder(&server).build().unwrap();
+        client.set_switch("RF:02", true).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn switch_not_found() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/action/deviceListGet"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "senrows": [{ "sid": "RF:02", "type": 24, "name": "Lamp", "status_ex": 1 }]
+            })))
+            .mount(&server)
+            .await;
+
+        let client = builder(&server).build().unwrap();
+        assert!(client.switch("RF:02").await.unwrap().on);
+        assert!(matches!(
+            client.switch("RF:99").await.unwrap_err(),
+            Error::DeviceNotFound(sid) if sid == "RF:99"
+        ));
+    }
+
+    #[tokio::test]
+    async fn set_target_temperature_posts_value() {
+        let server = MockServer::start().await;
+
+        mount_token(&server).await;
+
+        Mock::given(method("POST"))
+            .and(path("/action/deviceThermostatPost"))
+            .and(body_string("id=ZS%3A02&set_temp=21.5"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({"result": 1, "message": "ok"})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = builder(&server).build().unwrap();
+        client.set_target_temperature("ZS:02", 21.5).await.unwrap();
+        assert!(matches!(
+            client
+                .set_target_temperature("ZS:02", 35.0)
+                .await
+                .unwrap_err(),
+            Error::InvalidArgument(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn move_shutter_posts_command() {
+        let server = MockServer::start().await;
+
+        mount_token(&server).await;
+
+        Mock::given(method("POST"))
+            .and(path("/action/deviceShutterPost"))
+            .and(body_string("id=ZS%3A02&action=close"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({"result": 1, "message": "ok"})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(path("/action/deviceDimmerPost"))
+            .and(body_string("id=ZS%3A03&level=60"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({"result": 1, "message": "ok"})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = builder(&server).build().unwrap();
+        client
+            .move_shutter("ZS:02", shutters::ShutterCommand::Close)
+            .await
+            .unwrap();
+        client.set_dimmer_level("ZS:03", 60).await.unwrap();
+        assert!(client.set_dimmer_level("ZS:03", 120).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn watch_yields_changes() {
+        use futures::StreamExt;
+
+        let server = MockServer::start().await;
+
+        let status = |mode: u8| {
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "forms": { "pcondform1": { "mode": mode }, "pcondform2": { "mode": 0 } }
+            }))
+        };
+        let devices = |state: u8| {
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "senrows": [{
+                    "sid": "RF:01", "type": 4, "name": "Door", "area": 1,
+                    "status_ex": state, "cond_ok": 1, "battery_ok": 1, "tamper_ok": 1
+                }]
+            }))
+        };
+
+        Mock::given(method("GET"))
+            .and(path("/action/panelCondGet"))
+            .respond_with(status(0))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/action/panelCondGet"))
+            .respond_with(status(1))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/action/deviceListGet"))
+            .respond_with(devices(0))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/action/deviceListGet"))
+            .respond_with(devices(1))
+            .mount(&server)
+            .await;
+
+        let client = builder(&server).build().unwrap();
+        let events: Vec<_> = client
+            .watch(Duration::from_millis(10))
+            .take(2)
+            .map(Result::unwrap)
+            .collect()
+            .await;
+
+        assert_eq!(
+            events,
+            [
+                Event::ModeChanged {
+                    area: Area::Area1,
+                    from: Some(Mode::Disarmed),
+                    to: Mode::Armed,
+                },
+                Event::StateChanged {
+                    sid: "RF:01".into(),
+                    name: "Door".into(),
+                    state: crate::State::Open,
+                },
+            ]
+        );
+    }
+
     #[tokio::test]
     async fn get_retries_on_session_timeout() {
         let server = MockServer::start().await;
@@ -285,11 +1305,10 @@ mod tests {
             .mount(&server)
             .await;
 
-        let base_url: reqwest::Url = format!("{}/action/", server.uri()).parse().unwrap();
-        let mut client = Client::with_base_url("user", "pass", base_url).unwrap();
+        let client = builder(&server).build().unwrap();
         let modes = client.get_status().await.unwrap();
-        assert_eq!(modes.area1, Mode::Disarmed);
-        assert_eq!(modes.area2, Mode::Armed);
+        assert_eq!(modes.mode(Area::Area1), Some(Mode::Disarmed));
+        assert_eq!(modes.mode(Area::Area2), Some(Mode::Armed));
     }
 
     #[tokio::test]
@@ -327,9 +1346,78 @@ mod tests {
             .mount(&server)
             .await;
 
-        let base_url: reqwest::Url = format!("{}/action/", server.uri()).parse().unwrap();
-        let mut client = Client::with_base_url("user", "pass", base_url).unwrap();
+        let client = builder(&server).build().unwrap();
         let result = client.change_mode(Area::Area1, Mode::Disarmed).await;
         assert!(result.is_ok());
     }
+
+    #[cfg(feature = "tracing")]
+    #[tokio::test]
+    async fn tracing_redacts_credentials() {
+        use std::sync::Mutex;
+
+        #[derive(Clone, Default)]
+        struct Capture(Arc<Mutex<Vec<u8>>>);
+
+        impl std::io::Write for Capture {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/action/tokenGet"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({"result": 1, "message": "secret-token"})),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/action/panelCondPost"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("<html>/action/login</html>"))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/action/panelCondPost"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({"result": 1, "message": ""})),
+            )
+            .mount(&server)
+            .await;
+
+        let capture = Capture::default();
+        let writer = capture.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::TRACE)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let client = Client::builder("user", "hunter2")
+            .base_url(format!("{}/action/", server.uri()))
+            .build()
+            .unwrap();
+        client
+            .change_mode(Area::Area1, Mode::Disarmed)
+            .await
+            .unwrap();
+
+        let log = String::from_utf8(capture.0.lock().unwrap().clone()).unwrap();
+        assert!(log.contains("action=panelCondPost"));
+        assert!(log.contains("session expired, refreshing token"));
+        assert!(log.contains("fetched a new token"));
+        assert!(log.contains("status=200"));
+        assert!(!log.contains("secret-token"));
+        assert!(!log.contains("hunter2"));
+    }
 }
@@ -1,8 +1,12 @@
-use num_enum::TryFromPrimitive;
+use num_enum::{FromPrimitive, IntoPrimitive};
 use strum::{Display, EnumString};
 
+use std::str::FromStr;
+
+use crate::errors::Error;
+
 /// Possible modes of an area
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Display, EnumString, TryFromPrimitive)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Display, EnumString, FromPrimitive, IntoPrimitive)]
 #[strum(ascii_case_insensitive)]
 #[cfg_attr(feature = "build-binary", derive(clap::ValueEnum))]
 #[repr(u8)]
@@ -21,12 +25,18 @@ pub enum Mode {
 
     /// Home 3
     Home3 = 4,
+
+    /// A mode the crate does not know about
+    #[num_enum(catch_all)]
+    #[strum(to_string = "Unknown({0})")]
+    #[cfg_attr(feature = "build-binary", value(skip))]
+    Unknown(u8),
 }
 
 /// Areas of an alarm panel
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Display, EnumString, TryFromPrimitive)]
-#[strum(ascii_case_insensitive)]
-#[cfg_attr(feature = "build-binary", derive(clap::ValueEnum))]
+///
+/// Parses from `area1`, `Area2`, … or the plain area number.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Display, FromPrimitive, IntoPrimitive)]
 #[repr(u8)]
 pub enum Area {
     /// Area 1
@@ -34,10 +44,29 @@ pub enum Area {
 
     /// Area 2
     Area2 = 2,
+
+    /// Any other area, for panels with more than two areas
+    #[num_enum(catch_all)]
+    #[strum(to_string = "Area{0}")]
+    Other(u8),
+}
+
+impl FromStr for Area {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Area, Error> {
+        let lower = s.to_ascii_lowercase();
+        let number = lower.strip_prefix("area").unwrap_or(&lower);
+
+        number
+            .parse::<u8>()
+            .map(Area::from)
+            .map_err(|_| Error::InvalidArgument(format!("invalid area: {s}")))
+    }
 }
 
 /// Possible status of an API response
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Display, EnumString, TryFromPrimitive)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Display, EnumString, FromPrimitive)]
 #[strum(ascii_case_insensitive)]
 #[cfg_attr(feature = "build-binary", derive(clap::ValueEnum))]
 #[repr(u8)]
@@ -47,10 +76,16 @@ pub enum Status {
 
     /// Ok
     Ok = 1,
+
+    /// A status the crate does not know about
+    #[num_enum(catch_all)]
+    #[strum(to_string = "Unknown({0})")]
+    #[cfg_attr(feature = "build-binary", value(skip))]
+    Unknown(u8),
 }
 
 /// Possible states of a binary sensor
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Display, EnumString, TryFromPrimitive)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Display, EnumString, FromPrimitive, IntoPrimitive)]
 #[strum(ascii_case_insensitive)]
 #[cfg_attr(feature = "build-binary", derive(clap::ValueEnum))]
 #[repr(u8)]
@@ -60,10 +95,19 @@ pub enum State {
 
     /// Open
     Open = 1,
+
+    /// A state the crate does not know about
+    #[num_enum(catch_all)]
+    #[strum(to_string = "Unknown({0})")]
+    #[cfg_attr(feature = "build-binary", value(skip))]
+    Unknown(u8),
 }
 
 /// Enumeration of Lupusec Alarm & Smarthome devices (incomplete)
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Display, EnumString, TryFromPrimitive)]
+///
+/// Device kinds the crate does not know about are represented as
+/// [`DeviceKind::Unknown`] with the raw type number.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Display, EnumString, FromPrimitive, IntoPrimitive)]
 #[strum(ascii_case_insensitive)]
 #[cfg_attr(feature = "build-binary", derive(clap::ValueEnum))]
 #[repr(u8)]
@@ -298,9 +342,156 @@ pub enum DeviceKind {
 
     /// Shocksensor
     Shocksensor = 93,
+
+    /// A device kind the crate does not know about
+    #[num_enum(catch_all)]
+    #[strum(to_string = "Unknown({0})")]
+    #[cfg_attr(feature = "build-binary", value(skip))]
+    Unknown(u8),
+}
+
+impl DeviceKind {
+    /// Whether the device is a door or window contact, which must be closed
+    /// to arm its area.
+    #[must_use]
+    pub fn is_contact(self) -> bool {
+        matches!(self, DeviceKind::DoorContact)
+    }
+
+    /// Whether the device is a power switch or smart plug that can be
+    /// turned on and off.
+    #[must_use]
+    pub fn is_switch(self) -> bool {
+        matches!(
+            self,
+            DeviceKind::PowerSwitch1
+                | DeviceKind::PowerSwitch2
+                | DeviceKind::PowerSwitchMeters
+                | DeviceKind::SmartSwitch
+        )
+    }
+
+    /// Whether the device is a thermostat or radiator valve.
+    #[must_use]
+    pub fn is_thermostat(self) -> bool {
+        matches!(
+            self,
+            DeviceKind::ThermostatDanfoss
+                | DeviceKind::ThermostatHorstmann
+                | DeviceKind::ThermostatElko
+                | DeviceKind::ThermostatRcs_
+                | DeviceKind::Thermostat
+                | DeviceKind::RadiatorThermostat
+        )
+    }
+
+    /// Whether the device is a shutter or awning.
+    #[must_use]
+    pub fn is_shutter(self) -> bool {
+        matches!(self, DeviceKind::Shutter | DeviceKind::Awning)
+    }
+
+    /// Whether the device is a dimmer.
+    #[must_use]
+    pub fn is_dimmer(self) -> bool {
+        matches!(self, DeviceKind::Dimmer1 | DeviceKind::Dimmer2)
+    }
+}
+
+/// Operating modes of a thermostat
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Display, EnumString, FromPrimitive, IntoPrimitive)]
+#[strum(ascii_case_insensitive)]
+#[cfg_attr(feature = "build-binary", derive(clap::ValueEnum))]
+#[repr(u8)]
+pub enum ThermostatMode {
+    /// Heating off (frost protection only)
+    Off = 0,
+
+    /// Follow the configured schedule
+    Auto = 1,
+
+    /// Hold the target temperature
+    Manual = 2,
+
+    /// Heat at full power for a short time
+    Boost = 3,
+
+    /// A mode the crate does not know about
+    #[num_enum(catch_all)]
+    #[strum(to_string = "Unknown({0})")]
+    #[cfg_attr(feature = "build-binary", value(skip))]
+    Unknown(u8),
+}
+
+/// Kinds of events recorded in the panel's history
+///
+/// The codes of the named kinds have not been verified against the history
+/// of a real panel; every other code is kept as [`EventKind::Unknown`], so
+/// no entry is dropped or mislabelled because of a missing kind. Please
+/// report codes that turn out to be wrong.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Display, EnumString, FromPrimitive)]
+#[strum(ascii_case_insensitive)]
+#[cfg_attr(feature = "build-binary", derive(clap::ValueEnum))]
+#[repr(u8)]
+pub enum EventKind {
+    /// Burglar alarm
+    Alarm = 1,
+
+    /// Area armed
+    Arm = 2,
+
+    /// Area disarmed
+    Disarm = 3,
+
+    /// Area switched to one of the home modes
+    Home = 4,
+
+    /// Device tampered with
+    Tamper = 5,
+
+    /// Device battery low
+    BatteryLow = 6,
+
+    /// Device or system fault
+    Fault = 7,
+
+    /// Fault, tamper or battery condition restored
+    Restore = 8,
+
+    /// Panic alarm
+    PanicAlarm = 9,
+
+    /// Fire alarm
+    FireAlarm = 10,
+
+    /// Medical alarm
+    MedicalAlarm = 11,
+
+    /// Water leak alarm
+    WaterAlarm = 12,
+
+    /// Power failure
+    PowerFailure = 13,
+
+    /// Device lost contact with the panel
+    Supervision = 14,
+
+    /// An event kind the crate does not know about, with the panel's code
+    #[num_enum(catch_all)]
+    #[strum(to_string = "Unknown({0})")]
+    #[cfg_attr(feature = "build-binary", value(skip))]
+    Unknown(u8),
 }
 
-impl_numeric_serde!(Mode, Area, Status, State, DeviceKind);
+impl_numeric_serde!(
+    Mode,
+    Area,
+    Status,
+    State,
+    DeviceKind,
+    EventKind,
+    ThermostatMode
+);
 
 #[cfg(test)]
 mod tests {
@@ -329,6 +520,13 @@ mod tests {
         assert_eq!("ARMED".parse::<Mode>().unwrap(), Mode::Armed);
     }
 
+    #[test]
+    fn mode_unknown_value() {
+        let mode: Mode = serde_json::from_str("\"9\"").unwrap();
+        assert_eq!(mode, Mode::Unknown(9));
+        assert_eq!(mode.to_string(), "Unknown(9)");
+    }
+
     #[test]
     fn mode_from_str_unknown() {
         assert!("unknown".parse::<Mode>().is_err());
@@ -340,12 +538,60 @@ mod tests {
         assert_eq!(area, Area::Area1);
     }
 
+    #[test]
+    fn area_beyond_two() {
+        let area: Area = serde_json::from_str("\"3\"").unwrap();
+        assert_eq!(area, Area::Other(3));
+        assert_eq!(area.to_string(), "Area3");
+        assert_eq!(u8::from(area), 3);
+    }
+
+    #[test]
+    fn area_from_str() {
+        assert_eq!("AREA2".parse::<Area>().unwrap(), Area::Area2);
+        assert_eq!("1".parse::<Area>().unwrap(), Area::Area1);
+        assert_eq!("area4".parse::<Area>().unwrap(), Area::Other(4));
+        assert!("kitchen".parse::<Area>().is_err());
+    }
+
     #[test]
     fn device_kind_unknown_value() {
+        let kind: DeviceKind = serde_json::from_str("\"94\"").unwrap();
+        assert_eq!(kind, DeviceKind::Unknown(94));
+        assert_eq!(kind.to_string(), "Unknown(94)");
+    }
+
+    #[test]
+    fn device_kind_out_of_range_fails() {
         let result: std::result::Result<DeviceKind, _> = serde_json::from_str("999");
         assert!(result.is_err());
     }
 
+    #[test]
+    fn state_unknown_value() {
+        let state: State = serde_json::from_str("7").unwrap();
+        assert_eq!(state, State::Unknown(7));
+    }
+
+    #[test]
+    fn status_unknown_value() {
+        let status: Status = serde_json::from_str("\"2\"").unwrap();
+        assert_eq!(status, Status::Unknown(2));
+        assert_eq!(status.to_string(), "Unknown(2)");
+    }
+
+    #[test]
+    fn event_kind_unknown_value() {
+        let kind: EventKind = serde_json::from_str("200").unwrap();
+        assert_eq!(kind, EventKind::Unknown(200));
+        assert_eq!(kind.to_string(), "Unknown(200)");
+
+        for code in [0, 15] {
+            let kind: EventKind = serde_json::from_value(code.into()).unwrap();
+            assert_eq!(kind, EventKind::Unknown(code));
+        }
+    }
+
     #[test]
     fn state_roundtrip() {
         let state: State = serde_json::from_str("0").unwrap();
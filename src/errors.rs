@@ -1,10 +1,17 @@
+use strum::IntoStaticStr;
 use thiserror::Error;
 
+use crate::constants::{Area, Mode};
+use crate::readiness::ArmReadiness;
+use crate::tls::Fingerprint;
+
 /// A `Result` alias where the `Err` case is `alarmate::Error`
 pub type Result<T = ()> = std::result::Result<T, Error>;
 
 /// Possible Errors
-#[derive(Error, Debug)]
+///
+/// `<&str>::from(&error)` gives the name of the variant, e.g. `"Http"`.
+#[derive(Error, Debug, IntoStaticStr)]
 pub enum Error {
     /// An error reported by the alarm panel
     #[error("error reported by the alarm panel: {0}")]
@@ -18,6 +25,40 @@ pub enum Error {
     #[error("the session expired")]
     SessionTimeout,
 
+    /// No device with the given sensor ID is known to the panel
+    #[error("no device with sensor ID {0}")]
+    DeviceNotFound(String),
+
+    /// An argument was rejected before sending it to the panel
+    #[error("invalid argument: {0}")]
+    InvalidArgument(String),
+
+    /// The panel accepted a mode change but did not switch to the requested
+    /// mode in time, e.g. because a door is open
+    #[error(
+        "{area} did not switch to {requested}: it is {}{}",
+        .actual.map_or_else(|| "not reported".into(), |mode| mode.to_string()),
+        .reason.as_ref().map(|reason| format!(" ({reason})")).unwrap_or_default()
+    )]
+    ModeNotReached {
+        /// The area whose mode was changed
+        area: Area,
+        /// The requested mode
+        requested: Mode,
+        /// The mode the panel reported last (`None` if it did not report the
+        /// area)
+        actual: Option<Mode>,
+        /// The panel's alert messages or its answer to the mode change, if
+        /// any
+        reason: Option<String>,
+    },
+
+    /// Arming was refused because devices of the area are open, faulty or
+    /// cannot be checked, see
+    /// [`ClientBuilder::guard_arming`](crate::ClientBuilder::guard_arming)
+    #[error("{0}")]
+    NotReady(ArmReadiness),
+
     /// An unexpected response error
     #[error("received an unexpected response with status {status}: {body}")]
     UnexpectedResponse {
@@ -35,9 +76,58 @@ pub enum Error {
     #[error("error converting a header from a string: {0}")]
     InvalidHeader(#[from] reqwest::header::InvalidHeaderValue),
 
+    /// An invalid panel host or base URL
+    #[error("invalid panel URL: {0}")]
+    InvalidUrl(#[from] url::ParseError),
+
+    /// An invalid certificate or fingerprint was supplied for pinning
+    #[error("invalid certificate pin: {0}")]
+    InvalidCertificate(String),
+
+    /// The panel presented a TLS certificate that does not match the pin
+    #[error(
+        "the panel's TLS certificate ({}) does not match the pinned fingerprint ({})",
+        display_fingerprint(.actual.as_ref()),
+        display_fingerprint(.expected.as_ref())
+    )]
+    CertificateMismatch {
+        /// The pinned fingerprint (`None` if nothing was recorded yet)
+        expected: Option<Fingerprint>,
+        /// The fingerprint of the presented certificate (`None` if the
+        /// connection did not use TLS)
+        actual: Option<Fingerprint>,
+    },
+
     /// A networking error communicating with the alarm panel
     #[error("error communicating with the panel: {0}")]
     Http(#[from] reqwest::Error),
+
+    /// An I/O error, e.g. when binding a listening socket
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// An error communicating with the MQTT broker
+    #[cfg(feature = "mqtt")]
+    #[error("error communicating with the MQTT broker: {0}")]
+    Mqtt(#[source] Box<dyn std::error::Error + Send + Sync>),
+}
+
+#[cfg(feature = "mqtt")]
+impl From<rumqttc::ClientError> for Error {
+    fn from(e: rumqttc::ClientError) -> Error {
+        Error::Mqtt(Box::new(e))
+    }
+}
+
+#[cfg(feature = "mqtt")]
+impl From<rumqttc::ConnectionError> for Error {
+    fn from(e: rumqttc::ConnectionError) -> Error {
+        Error::Mqtt(Box::new(e))
+    }
+}
+
+fn display_fingerprint(fingerprint: Option<&Fingerprint>) -> String {
+    fingerprint.map_or_else(|| "none".into(), ToString::to_string)
 }
 
 impl Error {
@@ -45,6 +135,24 @@ impl Error {
     pub(crate) fn is_session_timeout(&self) -> bool {
         matches!(*self, Error::SessionTimeout)
     }
+
+    /// Whether the error is likely temporary: a connection failure, a
+    /// timeout, a connection reset or a 5xx response.
+    #[must_use]
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Error::Http(e) => e.is_connect() || e.is_timeout() || e.is_request() || e.is_body(),
+            Error::UnexpectedResponse { status, .. } => status.is_server_error(),
+            _ => false,
+        }
+    }
+
+    /// Whether the connection to the panel could not be established, i.e.
+    /// the request was never sent.
+    #[must_use]
+    pub(crate) fn is_connect(&self) -> bool {
+        matches!(self, Error::Http(e) if e.is_connect())
+    }
 }
 
 #[cfg(test)]
@@ -66,6 +174,18 @@ mod tests {
         );
     }
 
+    #[test]
+    fn transient_errors() {
+        let unexpected = |status| Error::UnexpectedResponse {
+            status,
+            body: String::new(),
+        };
+        assert!(unexpected(reqwest::StatusCode::BAD_GATEWAY).is_transient());
+        assert!(!unexpected(reqwest::StatusCode::NOT_FOUND).is_transient());
+        assert!(!Error::Unauthorized.is_transient());
+        assert!(!Error::SessionTimeout.is_transient());
+    }
+
     #[test]
     fn display_messages() {
         assert_eq!(Error::SessionTimeout.to_string(), "the session expired");
@@ -74,5 +194,15 @@ mod tests {
             "unauthorized: invalid credentials"
         );
         assert!(Error::Panel("oops".into()).to_string().contains("oops"));
+        assert_eq!(
+            Error::ModeNotReached {
+                area: Area::Area1,
+                requested: Mode::Armed,
+                actual: Some(Mode::Disarmed),
+                reason: Some("Door open".into()),
+            }
+            .to_string(),
+            "Area1 did not switch to Armed: it is Disarmed (Door open)"
+        );
     }
 }
@@ -0,0 +1,479 @@
+//! A Prometheus exporter for the panel.
+//!
+//! Requires the `exporter` feature. See [`Exporter`] for the metrics.
+
+use http_body_util::Full;
+use hyper::body::{Bytes, Incoming};
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode, header};
+use hyper_util::rt::TokioIo;
+use strum::IntoEnumIterator;
+use tokio::net::TcpListener;
+
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::client::Client;
+use crate::constants::{Mode, Status};
+use crate::errors::Result;
+use crate::resources::{devices::Device, panel::Modes, readings::Quantity};
+
+/// The content type of the Prometheus text format
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// All modes, to export every area's mode as a state set
+const MODES: [Mode; 5] = [
+    Mode::Disarmed,
+    Mode::Armed,
+    Mode::Home1,
+    Mode::Home2,
+    Mode::Home3,
+];
+
+/// Serves the state of the panel as Prometheus metrics on `/metrics`.
+///
+/// Every scrape queries the panel. The following metrics are exported:
+///
+/// | Metric | Labels | Value |
+/// |---|---|---|
+/// | `alarmate_up` | | 1 if the panel answered the last scrape |
+/// | `alarmate_scrape_duration_seconds` | | time taken to query the panel |
+/// | `alarmate_area_mode` | `area`, `mode` | 1 for the current mode of the area, else 0 |
+/// | `alarmate_area_alarm` | `area` | 1 while an alarm is in progress |
+/// | `alarmate_device_state` | `sid`, `name`, `kind` | 0 closed, 1 open, or the raw state |
+/// | `alarmate_device_battery_ok` | `sid`, `name`, `kind` | 1 if the battery is ok |
+/// | `alarmate_device_tamper_ok` | `sid`, `name`, `kind` | 1 if the device is not tampered with |
+/// | `alarmate_device_condition_ok` | `sid`, `name`, `kind` | 1 if the device is in order |
+/// | `alarmate_device_<reading>_<unit>` | `sid`, `name`, `kind` | a sensor reading, e.g. `alarmate_device_temperature_celsius` |
+/// | `alarmate_scrapes_total` | | number of scrapes |
+/// | `alarmate_scrape_errors_total` | `error` | failed scrapes by [`Error`](crate::Error) variant |
+/// | `alarmate_panel_request_errors_total` | `action`, `error` | failed panel requests by action and [`Error`](crate::Error) variant |
+///
+/// ```no_run
+/// # async fn run(client: alarmate::Client) -> alarmate::Result {
+/// alarmate::exporter::Exporter::new(client)
+///     .serve("127.0.0.1:9798".parse().unwrap())
+///     .await
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Exporter {
+    client: Client,
+    counters: Arc<Mutex<Counters>>,
+}
+
+#[derive(Default)]
+struct Counters {
+    scrapes: u64,
+    scrape_errors: BTreeMap<&'static str, u64>,
+    request_errors: BTreeMap<(&'static str, &'static str), u64>,
+}
+
+impl Exporter {
+    /// Create an exporter for the panel behind `client`.
+    #[must_use]
+    pub fn new(client: Client) -> Exporter {
+        Exporter {
+            client,
+            counters: Arc::default(),
+        }
+    }
+
+    /// Serve `/metrics` on `addr` until an error occurs while accepting
+    /// connections.
+    pub async fn serve(self, addr: SocketAddr) -> Result {
+        let listener = TcpListener::bind(addr).await?;
+        self.serve_listener(listener).await
+    }
+
+    async fn serve_listener(self, listener: TcpListener) -> Result {
+        loop {
+            let (stream, _) = listener.accept().await?;
+            let exporter = self.clone();
+
+            tokio::spawn(async move {
+                let service = service_fn(move |req| {
+                    let exporter = exporter.clone();
+                    async move { Ok::<_, Infallible>(exporter.respond(&req).await) }
+                });
+
+                // A client hanging up mid-request is not an error of the exporter
+                let _ = hyper::server::conn::http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await;
+            });
+        }
+    }
+
+    async fn respond(&self, req: &Request<Incoming>) -> Response<Full<Bytes>> {
+        let response = Response::builder();
+
+        let response = match (req.method(), req.uri().path()) {
+            (&Method::GET, "/metrics") => response
+                .header(header::CONTENT_TYPE, CONTENT_TYPE)
+                .body(Full::from(self.render().await)),
+            (&Method::GET, _) => response
+                .status(StatusCode::NOT_FOUND)
+                .body(Full::from("not found, try /metrics\n")),
+            _ => response
+                .status(StatusCode::METHOD_NOT_ALLOWED)
+                .body(Full::default()),
+        };
+
+        response.expect("static response parts are valid")
+    }
+
+    /// Query the panel and render the metrics in the Prometheus text format.
+    pub async fn render(&self) -> String {
+        let start = Instant::now();
+        let (modes, devices) = futures::join!(self.client.get_status(), self.client.list_devices());
+        let duration = start.elapsed();
+
+        let mut out = String::new();
+        {
+            let mut counters = self.counters.lock().unwrap();
+            counters.scrapes += 1;
+
+            let failures = [
+                ("panelCondGet", modes.as_ref().err()),
+                ("deviceListGet", devices.as_ref().err()),
+            ];
+            for (action, error) in failures {
+                if let Some(error) = error {
+                    let variant = <&str>::from(error);
+                    *counters
+                        .request_errors
+                        .entry((action, variant))
+                        .or_default() += 1;
+                }
+            }
+            if let Some(error) = modes.as_ref().err().or(devices.as_ref().err()) {
+                *counters
+                    .scrape_errors
+                    .entry(<&str>::from(error))
+                    .or_default() += 1;
+            }
+
+            let up = modes.is_ok() && devices.is_ok();
+            gauge(
+                &mut out,
+                "alarmate_up",
+                "Whether the panel answered the last scrape",
+            );
+            sample(&mut out, "alarmate_up", &[], u8::from(up));
+            gauge(
+                &mut out,
+                "alarmate_scrape_duration_seconds",
+                "Time taken to query the panel",
+            );
+            sample(
+                &mut out,
+                "alarmate_scrape_duration_seconds",
+                &[],
+                duration.as_secs_f64(),
+            );
+
+            render_counters(&mut out, &counters);
+        }
+
+        if let Ok(modes) = &modes {
+            render_modes(&mut out, modes);
+        }
+        if let Ok(devices) = &devices {
+            render_devices(&mut out, devices);
+        }
+
+        out
+    }
+}
+
+fn render_counters(out: &mut String, counters: &Counters) {
+    counter(out, "alarmate_scrapes_total", "Number of scrapes");
+    sample(out, "alarmate_scrapes_total", &[], counters.scrapes);
+
+    counter(
+        out,
+        "alarmate_scrape_errors_total",
+        "Failed scrapes by error",
+    );
+    for (error, count) in &counters.scrape_errors {
+        sample(
+            out,
+            "alarmate_scrape_errors_total",
+            &[("error", error)],
+            count,
+        );
+    }
+
+    counter(
+        out,
+        "alarmate_panel_request_errors_total",
+        "Failed panel requests by action and error",
+    );
+    for ((action, error), count) in &counters.request_errors {
+        let labels = [("action", *action), ("error", *error)];
+        sample(out, "alarmate_panel_request_errors_total", &labels, count);
+    }
+}
+
+fn render_modes(out: &mut String, modes: &Modes) {
+    gauge(out, "alarmate_area_mode", "The current mode of an area");
+    for condition in &modes.areas {
+        let area = u8::from(condition.area).to_string();
+        for mode in MODES {
+            let labels = [("area", area.as_str()), ("mode", &mode.to_string())];
+            sample(
+                out,
+                "alarmate_area_mode",
+                &labels,
+                u8::from(condition.mode == mode),
+            );
+        }
+    }
+
+    gauge(
+        out,
+        "alarmate_area_alarm",
+        "Whether an alarm is in progress in an area",
+    );
+    for condition in &modes.areas {
+        let area = u8::from(condition.area).to_string();
+        sample(
+            out,
+            "alarmate_area_alarm",
+            &[("area", &area)],
+            u8::from(condition.alarm),
+        );
+    }
+}
+
+/// Selects a status field of a device
+type StatusOf = fn(&Device) -> Status;
+
+fn render_devices(out: &mut String, devices: &[Device]) {
+    let kinds: Vec<_> = devices
+        .iter()
+        .map(|device| device.kind.to_string())
+        .collect();
+    let labels = |i: usize| {
+        let device = &devices[i];
+        [
+            ("sid", device.sid.as_str()),
+            ("name", device.name.as_str()),
+            ("kind", kinds[i].as_str()),
+        ]
+    };
+
+    gauge(
+        out,
+        "alarmate_device_state",
+        "The state of a device: 0 closed, 1 open",
+    );
+    for (i, device) in devices.iter().enumerate() {
+        sample(
+            out,
+            "alarmate_device_state",
+            &labels(i),
+            u8::from(device.state),
+        );
+    }
+
+    let statuses: [(&str, &str, StatusOf); 3] = [
+        (
+            "alarmate_device_battery_ok",
+            "Whether the battery of a device is ok",
+            |d| d.battery,
+        ),
+        (
+            "alarmate_device_tamper_ok",
+            "Whether a device is not tampered with",
+            |d| d.tamper,
+        ),
+        (
+            "alarmate_device_condition_ok",
+            "Whether a device is in order",
+            |d| d.condition,
+        ),
+    ];
+    for (name, help, status) in statuses {
+        gauge(out, name, help);
+        for (i, device) in devices.iter().enumerate() {
+            sample(
+                out,
+                name,
+                &labels(i),
+                u8::from(status(device) == Status::Ok),
+            );
+        }
+    }
+
+    for quantity in Quantity::iter() {
+        let name = reading_metric(quantity);
+        let mut readings = devices
+            .iter()
+            .enumerate()
+            .filter_map(|(i, device)| Some((i, device.readings.get(quantity)?)))
+            .peekable();
+        if readings.peek().is_none() {
+            continue;
+        }
+
+        gauge(out, &name, &format!("{quantity} in {}", quantity.unit()));
+        for (i, value) in readings {
+            sample(out, &name, &labels(i), value);
+        }
+    }
+}
+
+/// The metric name of a reading, with the unit as suffix as the Prometheus
+/// naming conventions ask for.
+fn reading_metric(quantity: Quantity) -> String {
+    let unit = match quantity {
+        Quantity::Temperature => "celsius",
+        Quantity::Humidity => "percent",
+        Quantity::Illuminance => "lux",
+        Quantity::Radon => "becquerels_per_cubic_meter",
+        Quantity::Power => "watts",
+        Quantity::Energy => "kilowatt_hours",
+        Quantity::Volume => "cubic_meters",
+    };
+    let quantity = quantity.to_string().to_ascii_lowercase();
+
+    format!("alarmate_device_{quantity}_{unit}")
+}
+
+fn gauge(out: &mut String, name: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}\n# TYPE {name} gauge");
+}
+
+fn counter(out: &mut String, name: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}\n# TYPE {name} counter");
+}
+
+fn sample(out: &mut String, name: &str, labels: &[(&str, &str)], value: impl std::fmt::Display) {
+    out.push_str(name);
+    if !labels.is_empty() {
+        let labels: Vec<_> = labels
+            .iter()
+            .map(|(key, value)| format!("{key}=\"{}\"", escape(value)))
+            .collect();
+        let _ = write!(out, "{{{}}}", labels.join(","));
+    }
+    let _ = writeln!(out, " {value}");
+}
+
+/// Escape a label value as required by the Prometheus text format.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::tests::builder;
+
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    async fn exporter(server: &MockServer) -> Exporter {
+        let client = builder(server).build().unwrap();
+        Exporter::new(client)
+    }
+
+    #[tokio::test]
+    async fn render_metrics() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/action/panelCondGet"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "forms": { "pcondform1": { "mode": 1, "alarm": 1 } }
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/action/deviceListGet"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "senrows": [{
+                    "sid": "RF:01", "type": 54, "name": "Living \"Room\"", "area": 1,
+                    "status_ex": 0, "cond_ok": 1, "battery_ok": 0, "tamper_ok": 1,
+                    "temperature": "21.5"
+                }]
+            })))
+            .mount(&server)
+            .await;
+
+        let metrics = exporter(&server).await.render().await;
+        let labels = r#"sid="RF:01",name="Living \"Room\"",kind="RoomSensor""#;
+
+        for line in [
+            "alarmate_up 1".to_owned(),
+            "alarmate_scrapes_total 1".to_owned(),
+            r#"alarmate_area_mode{area="1",mode="Armed"} 1"#.to_owned(),
+            r#"alarmate_area_mode{area="1",mode="Disarmed"} 0"#.to_owned(),
+            r#"alarmate_area_alarm{area="1"} 1"#.to_owned(),
+            format!("alarmate_device_state{{{labels}}} 0"),
+            format!("alarmate_device_battery_ok{{{labels}}} 0"),
+            format!("alarmate_device_tamper_ok{{{labels}}} 1"),
+            format!("alarmate_device_temperature_celsius{{{labels}}} 21.5"),
+            "# TYPE alarmate_device_temperature_celsius gauge".to_owned(),
+        ] {
+            assert!(metrics.lines().any(|l| l == line), "{line}\n{metrics}");
+        }
+        assert!(!metrics.contains("humidity"));
+    }
+
+    #[tokio::test]
+    async fn count_errors_by_variant() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/action/panelCondGet"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/action/deviceListGet"))
+            .respond_with(ResponseTemplate::new(401))
+            .mount(&server)
+            .await;
+
+        let exporter = exporter(&server).await;
+        exporter.render().await;
+        let metrics = exporter.render().await;
+
+        for line in [
+            "alarmate_up 0",
+            "alarmate_scrapes_total 2",
+            r#"alarmate_scrape_errors_total{error="UnexpectedResponse"} 2"#,
+            r#"alarmate_panel_request_errors_total{action="panelCondGet",error="UnexpectedResponse"} 2"#,
+            r#"alarmate_panel_request_errors_total{action="deviceListGet",error="Unauthorized"} 2"#,
+        ] {
+            assert!(metrics.lines().any(|l| l == line), "{line}\n{metrics}");
+        }
+        assert!(!metrics.contains("alarmate_area_mode{"));
+    }
+
+    #[tokio::test]
+    async fn serve_metrics_over_http() {
+        let server = MockServer::start().await;
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(exporter(&server).await.serve_listener(listener));
+
+        let res = reqwest::get(format!("http://{addr}/metrics"))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.headers()["content-type"], CONTENT_TYPE);
+        assert!(res.text().await.unwrap().contains("alarmate_up 0"));
+
+        let res = reqwest::get(format!("http://{addr}/")).await.unwrap();
+        assert_eq!(res.status(), 404);
+    }
+}
@@ -8,17 +8,73 @@
 //!   let ip_address = "192.168.178.10".parse().unwrap();
 //!   let client = alarmate::Client::new("admin", "changeme", ip_address).unwrap();
 //! ```
+//!
+//! Use [`Client::builder`] to reach the panel by hostname, port or base URL and
+//! to configure timeouts and proxies:
+//!
+//! ```rust
+//!   let client = alarmate::Client::builder("admin", "changeme")
+//!       .host("lupusec.local")
+//!       .timeout(std::time::Duration::from_secs(10))
+//!       .build()
+//!       .unwrap();
+//! ```
+//!
+//! ## TLS backends
+//!
+//! The `native-tls` feature (the default) uses the platform's TLS library,
+//! OpenSSL on Linux. For static builds without OpenSSL, disable the default
+//! features and enable `rustls` instead. Exactly one of the two must be
+//! enabled; certificate pinning works with either.
 
 #![deny(missing_docs)]
 
+#[cfg(all(feature = "native-tls", feature = "rustls"))]
+compile_error!(
+    "the `native-tls` and `rustls` features are mutually exclusive, use `default-features = false` to select `rustls`"
+);
+
+#[cfg(not(any(feature = "native-tls", feature = "rustls")))]
+compile_error!("enable either the `native-tls` or the `rustls` feature");
+
 #[macro_use]
 mod utils;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+mod builder;
 mod client;
 mod constants;
 mod errors;
+#[cfg(feature = "exporter")]
+pub mod exporter;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+mod password;
+mod readiness;
+mod recording;
 mod resources;
+mod retry;
+#[cfg(feature = "simulator")]
+pub mod simulator;
+mod tls;
+mod watch;
 
+pub use builder::{ClientBuilder, Scheme};
 pub use client::Client;
-pub use constants::{Area, DeviceKind, Mode};
+pub use constants::{Area, DeviceKind, EventKind, Mode, State, Status, ThermostatMode};
 pub use errors::{Error, Result};
-pub use resources::{devices::Device, panel::Modes};
+pub use password::Password;
+pub use readiness::{ArmReadiness, Blocker, Problem};
+pub use resources::{
+    devices::Device,
+    dimmers::Dimmer,
+    history::{HistoryEntry, HistoryFilter},
+    panel::{AreaCondition, Modes},
+    readings::{Quantity, Readings},
+    shutters::{Shutter, ShutterCommand},
+    switches::Switch,
+    thermostats::Thermostat,
+};
+pub use retry::RetryPolicy;
+pub use tls::Fingerprint;
+pub use watch::Event;
@@ -0,0 +1,344 @@
+//! Home Assistant MQTT discovery configs for the topics of the bridge.
+//!
+//! See <https://www.home-assistant.io/integrations/mqtt/#mqtt-discovery>.
+
+use serde_json::{Value, json};
+
+use crate::constants::{Area, DeviceKind, Mode};
+use crate::resources::{devices::Device, readings::Quantity};
+
+/// The Home Assistant alarm state for each mode; the payload Home Assistant
+/// sends to arm the area in that state is the mode itself. Any other mode,
+/// e.g. [`Mode::Unknown`], maps to [`UNKNOWN_STATE`].
+const ALARM_STATES: [(Mode, &str); 5] = [
+    (Mode::Disarmed, "disarmed"),
+    (Mode::Armed, "armed_away"),
+    (Mode::Home1, "armed_home"),
+    (Mode::Home2, "armed_night"),
+    (Mode::Home3, "armed_vacation"),
+];
+
+/// The payload Home Assistant takes for an unknown alarm state
+const UNKNOWN_STATE: &str = "None";
+
+/// Builds the discovery configs for one bridge.
+pub(super) struct Discovery<'a> {
+    /// The discovery prefix Home Assistant listens on, usually `homeassistant`
+    pub(super) prefix: &'a str,
+    /// The base topic of the bridge
+    pub(super) base_topic: &'a str,
+}
+
+impl Discovery<'_> {
+    /// The `alarm_control_panel` config of an area.
+    pub(super) fn area(&self, area: Area) -> (String, Value) {
+        let area = u8::from(area);
+        let mapping: Vec<_> = ALARM_STATES
+            .iter()
+            .map(|(mode, state)| format!("'{mode}': '{state}'"))
+            .collect();
+
+        let config = json!({
+            "name": format!("Area {area}"),
+            "unique_id": format!("{}_area_{area}", self.node_id()),
+            "state_topic": format!("{}/area/{area}/mode", self.base_topic),
+            "command_topic": format!("{}/area/{area}/mode/set", self.base_topic),
+            "value_template": format!(
+                "{{{{ {{{}}}.get(value, '{UNKNOWN_STATE}') }}}}",
+                mapping.join(", ")
+            ),
+            "payload_disarm": Mode::Disarmed.to_string(),
+            "payload_arm_away": Mode::Armed.to_string(),
+            "payload_arm_home": Mode::Home1.to_string(),
+            "payload_arm_night": Mode::Home2.to_string(),
+            "payload_arm_vacation": Mode::Home3.to_string(),
+            "supported_features": ["arm_away", "arm_home", "arm_night", "arm_vacation"],
+            "code_arm_required": false,
+            "code_disarm_required": false,
+            "availability_topic": format!("{}/status", self.base_topic),
+            "device": self.panel(),
+        });
+
+        (
+            self.topic("alarm_control_panel", &format!("area_{area}")),
+            config,
+        )
+    }
+
+    /// The configs of the entities of a device: a `switch` for switches, a
+    /// `binary_sensor` for contacts and detectors, a `sensor` per reading and
+    /// diagnostic `binary_sensor`s for the battery, tamper and condition
+    /// status.
+    pub(super) fn device(&self, device: &Device) -> Vec<(String, Value)> {
+        let mut configs = Vec::new();
+
+        if device.kind.is_switch() {
+            configs.push(self.entity(
+                device,
+                "switch",
+                "switch",
+                json!({
+                    "name": null,
+                    "command_topic": self.device_topic(device, "switch/set"),
+                    "payload_on": "on",
+                    "payload_off": "off",
+                    "state_on": "on",
+                    "state_off": "off",
+                }),
+            ));
+        } else if let Some(class) = binary_sensor_class(device.kind) {
+            configs.push(self.entity(
+                device,
+                "binary_sensor",
+                "state",
+                json!({
+                    "name": null,
+                    "device_class": class,
+                    "payload_on": "Open",
+                    "payload_off": "Closed",
+                }),
+            ));
+        }
+
+        for (quantity, _) in device.readings.iter() {
+            let field = quantity.to_string().to_ascii_lowercase();
+            let mut config = json!({
+                "name": quantity.to_string(),
+                "unit_of_measurement": quantity.unit(),
+                "state_class": state_class(quantity),
+            });
+            if let Some(class) = sensor_class(quantity, device.kind) {
+                config["device_class"] = class.into();
+            }
+            configs.push(self.entity(device, "sensor", &field, config));
+        }
+
+        for (field, name, class) in [
+            ("battery", "Battery", "battery"),
+            ("tamper", "Tamper", "tamper"),
+            ("condition", "Condition", "problem"),
+        ] {
+            configs.push(self.entity(
+                device,
+                "binary_sensor",
+                field,
+                json!({
+                    "name": name,
+                    "device_class": class,
+                    "entity_category": "diagnostic",
+                    "payload_on": "Error",
+                    "payload_off": "Ok",
+                }),
+            ));
+        }
+
+        configs
+    }
+
+    /// Complete the config of an entity reading the `field` topic of a device.
+    fn entity(
+        &self,
+        device: &Device,
+        component: &str,
+        field: &str,
+        mut config: Value,
+    ) -> (String, Value) {
+        let object_id = format!("{}_{field}", sanitize(&device.sid));
+
+        config["unique_id"] = format!("{}_{object_id}", self.node_id()).into();
+        config["state_topic"] = self.device_topic(device, field).into();
+        config["availability_topic"] = format!("{}/status", self.base_topic).into();
+        config["device"] = json!({
+            "identifiers": [format!("{}_{}", self.node_id(), sanitize(&device.sid))],
+            "name": device.name,
+            "model": device.kind.to_string(),
+            "manufacturer": "LUPUSEC",
+            "via_device": format!("{}_panel", self.node_id()),
+        });
+
+        (self.topic(component, &object_id), config)
+    }
+
+    /// The Home Assistant device all areas belong to.
+    fn panel(&self) -> Value {
+        json!({
+            "identifiers": [format!("{}_panel", self.node_id())],
+            "name": "LUPUSEC XT2",
+            "model": "XT2",
+            "manufacturer": "LUPUSEC",
+        })
+    }
+
+    fn device_topic(&self, device: &Device, field: &str) -> String {
+        format!("{}/device/{}/{field}", self.base_topic, device.sid)
+    }
+
+    fn topic(&self, component: &str, object_id: &str) -> String {
+        format!(
+            "{}/{component}/{}/{object_id}/config",
+            self.prefix,
+            self.node_id()
+        )
+    }
+
+    /// Identifies the bridge, so several bridges can share one Home
+    /// Assistant.
+    fn node_id(&self) -> String {
+        sanitize(self.base_topic)
+    }
+}
+
+/// Replace everything but ASCII alphanumerics, `_` and `-`, which are the
+/// only characters Home Assistant allows in node and object IDs.
+fn sanitize(id: &str) -> String {
+    id.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// The device class of the binary sensor for the state of a device, or
+/// `None` if the state of the kind is not meaningful (e.g. keypads, sirens,
+/// meters).
+fn binary_sensor_class(kind: DeviceKind) -> Option<&'static str> {
+    let class = match kind {
+        DeviceKind::DoorContact => "door",
+        DeviceKind::SensorInput => "opening",
+        DeviceKind::WaterSensor => "moisture",
+        DeviceKind::MotionDetector | DeviceKind::OutdoorMotionDetector | DeviceKind::PirCamera => {
+            "motion"
+        }
+        DeviceKind::SmokedetectorAndHeatDetector | DeviceKind::SmokeDetector => "smoke",
+        DeviceKind::HeatDetector1 | DeviceKind::HeatDetector2 => "heat",
+        DeviceKind::GasDetector => "gas",
+        DeviceKind::CoDetector => "carbon_monoxide",
+        DeviceKind::GlassBreakSensor
+        | DeviceKind::GlassBreakDetector
+        | DeviceKind::Shocksensor
+        | DeviceKind::SuddenMotionSensor1
+        | DeviceKind::SuddenMotionSensor2
+        | DeviceKind::SuddenMotionSensor3 => "vibration",
+        DeviceKind::DoorLock | DeviceKind::DoorlockSecure | DeviceKind::DoorlockYale => "lock",
+        _ => return None,
+    };
+
+    Some(class)
+}
+
+/// The device class of the sensor for a reading, if Home Assistant has one.
+fn sensor_class(quantity: Quantity, kind: DeviceKind) -> Option<&'static str> {
+    match quantity {
+        Quantity::Temperature => Some("temperature"),
+        Quantity::Humidity => Some("humidity"),
+        Quantity::Illuminance => Some("illuminance"),
+        Quantity::Power => Some("power"),
+        Quantity::Energy => Some("energy"),
+        Quantity::Volume if kind == DeviceKind::GasMeters => Some("gas"),
+        Quantity::Volume if kind == DeviceKind::WaterMeter => Some("water"),
+        Quantity::Volume | Quantity::Radon => None,
+    }
+}
+
+fn state_class(quantity: Quantity) -> &'static str {
+    match quantity {
+        Quantity::Energy | Quantity::Volume => "total_increasing",
+        _ => "measurement",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DISCOVERY: Discovery<'static> = Discovery {
+        prefix: "homeassistant",
+        base_topic: "home/alarm",
+    };
+
+    fn device(kind: u8, readings: Value) -> Device {
+        let mut json = json!({
+            "sid": "RF:01", "type": kind, "name": "Hallway", "area": 1,
+            "status_ex": 0, "cond_ok": 1, "battery_ok": 1, "tamper_ok": 1
+        });
+        json.as_object_mut()
+            .unwrap()
+            .extend(readings.as_object().unwrap().clone());
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn area_config() {
+        let (topic, config) = DISCOVERY.area(Area::Area2);
+        assert_eq!(
+            topic,
+            "homeassistant/alarm_control_panel/home_alarm/area_2/config"
+        );
+        assert_eq!(config["state_topic"], "home/alarm/area/2/mode");
+        assert_eq!(config["command_topic"], "home/alarm/area/2/mode/set");
+        assert_eq!(config["payload_arm_home"], "Home1");
+        assert_eq!(config["payload_disarm"], "Disarmed");
+        assert_eq!(config["unique_id"], "home_alarm_area_2");
+        assert_eq!(
+            config["value_template"],
+            "{{ {'Disarmed': 'disarmed', 'Armed': 'armed_away', 'Home1': 'armed_home', \
+             'Home2': 'armed_night', 'Home3': 'armed_vacation'}.get(value, 'None') }}"
+        );
+    }
+
+    #[test]
+    fn door_contact_configs() {
+        let configs = DISCOVERY.device(&device(4, json!({})));
+        let topics: Vec<_> = configs.iter().map(|(topic, _)| topic.as_str()).collect();
+        assert_eq!(
+            topics,
+            [
+                "homeassistant/binary_sensor/home_alarm/RF_01_state/config",
+                "homeassistant/binary_sensor/home_alarm/RF_01_battery/config",
+                "homeassistant/binary_sensor/home_alarm/RF_01_tamper/config",
+                "homeassistant/binary_sensor/home_alarm/RF_01_condition/config",
+            ]
+        );
+
+        let state = &configs[0].1;
+        assert_eq!(state["device_class"], "door");
+        assert_eq!(state["state_topic"], "home/alarm/device/RF:01/state");
+        assert_eq!(state["payload_on"], "Open");
+        assert_eq!(state["device"]["name"], "Hallway");
+        assert_eq!(state["device"]["model"], "DoorContact");
+    }
+
+    #[test]
+    fn switch_and_sensor_configs() {
+        let configs = DISCOVERY.device(&device(48, json!({ "power": "12.5", "energy": 3 })));
+
+        let switch = &configs[0];
+        assert_eq!(
+            switch.0,
+            "homeassistant/switch/home_alarm/RF_01_switch/config"
+        );
+        assert_eq!(
+            switch.1["command_topic"],
+            "home/alarm/device/RF:01/switch/set"
+        );
+        assert_eq!(switch.1["state_topic"], "home/alarm/device/RF:01/switch");
+
+        let power = &configs[1].1;
+        assert_eq!(power["device_class"], "power");
+        assert_eq!(power["unit_of_measurement"], "W");
+        assert_eq!(power["state_topic"], "home/alarm/device/RF:01/power");
+
+        let energy = &configs[2].1;
+        assert_eq!(energy["state_class"], "total_increasing");
+    }
+
+    #[test]
+    fn keypads_have_no_state_entity() {
+        let configs = DISCOVERY.device(&device(15, json!({})));
+        assert_eq!(configs.len(), 3);
+    }
+}
@@ -0,0 +1,722 @@
+//! A bridge between the panel and an MQTT broker.
+//!
+//! Requires the `mqtt` feature. See [`Bridge`] for the topic layout.
+
+mod discovery;
+
+use rumqttc::{AsyncClient, ConnectionError, EventLoop, LastWill, Packet, QoS};
+use tokio::sync::mpsc;
+use tokio::task::{JoinHandle, JoinSet};
+use tokio::time::MissedTickBehavior;
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+pub use rumqttc::MqttOptions;
+
+use crate::client::Client;
+use crate::constants::{Area, Mode, State, ThermostatMode};
+use crate::errors::{Error, Result};
+use crate::resources::{devices::Device, panel::Modes, shutters::ShutterCommand};
+
+use discovery::Discovery;
+
+/// The payload of the `status` topic while the bridge is connected
+const ONLINE: &str = "online";
+
+/// The payload of the `status` topic after the bridge disconnected
+const OFFLINE: &str = "offline";
+
+/// How long to wait before reconnecting to the broker
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Publishes the state of the panel to an MQTT broker and forwards commands
+/// received on `…/set` topics to the panel.
+///
+/// All topics are below the base topic (`alarmate` by default). State topics
+/// are retained and only published when their payload changes. Those of
+/// removed devices are cleared:
+///
+/// | Topic | Payload |
+/// |---|---|
+/// | `status` | `online`, or `offline` once the bridge is gone (last will) |
+/// | `area/<n>/mode` | `Disarmed`, `Armed`, `Home1`, `Home2` or `Home3` |
+/// | `device/<sid>/state` | `Closed`, `Open` or `Unknown(<n>)` |
+/// | `device/<sid>/battery`, `…/tamper`, `…/condition` | `Ok` or `Error` |
+/// | `device/<sid>/switch` | `on` or `off`, for switches and smart plugs |
+/// | `device/<sid>/<quantity>` | a sensor reading, e.g. `…/temperature` |
+/// | `error` | why the last poll or command failed (not retained) |
+///
+/// Commands:
+///
+/// | Topic | Payload |
+/// |---|---|
+/// | `area/<n>/mode/set` | a [`Mode`], e.g. `armed` |
+/// | `device/<sid>/switch/set` | `on` or `off` |
+/// | `device/<sid>/target_temperature/set` | the target temperature in °C |
+/// | `device/<sid>/thermostat_mode/set` | a [`ThermostatMode`], e.g. `auto` |
+/// | `device/<sid>/shutter/set` | `open`, `close`, `stop` or a position in percent |
+/// | `device/<sid>/level/set` | the dimmer brightness in percent |
+///
+/// With [`Bridge::home_assistant`], Home Assistant discovery configs are
+/// published as well, so every area shows up as an alarm control panel and
+/// every device with its switch, sensors and diagnostics.
+///
+/// ```no_run
+/// use alarmate::{Client, mqtt::{Bridge, MqttOptions}};
+/// use std::time::Duration;
+///
+/// # async fn run(client: Client) -> alarmate::Result {
+/// let options = MqttOptions::new("alarmate", "localhost", 1883);
+/// Bridge::new(client, options)
+///     .base_topic("home/alarm")
+///     .interval(Duration::from_secs(10))
+///     .run()
+///     .await
+/// # }
+/// ```
+#[must_use]
+pub struct Bridge {
+    client: Client,
+    options: MqttOptions,
+    base_topic: String,
+    interval: Duration,
+    discovery_prefix: Option<String>,
+}
+
+/// A command received on a `…/set` topic
+#[derive(Debug, PartialEq)]
+enum Command {
+    Mode(Area, Mode),
+    Switch(String, bool),
+    TargetTemperature(String, f64),
+    ThermostatMode(String, ThermostatMode),
+    Shutter(String, ShutterCommand),
+    Level(String, u8),
+}
+
+impl Bridge {
+    /// Create a bridge between the panel behind `client` and the broker
+    /// described by `options`.
+    ///
+    /// The last will of `options` is replaced by the `offline` message of the
+    /// `status` topic.
+    pub fn new(client: Client, options: MqttOptions) -> Bridge {
+        Bridge {
+            client,
+            options,
+            base_topic: "alarmate".to_owned(),
+            interval: Duration::from_secs(5),
+            discovery_prefix: None,
+        }
+    }
+
+    /// Set the topic all other topics are nested below. Defaults to
+    /// `alarmate`.
+    pub fn base_topic(mut self, topic: impl Into<String>) -> Bridge {
+        self.base_topic = topic.into().trim_end_matches('/').to_owned();
+        self
+    }
+
+    /// Set how often the panel is polled. Defaults to 5 seconds.
+    pub fn interval(mut self, interval: Duration) -> Bridge {
+        self.interval = interval;
+        self
+    }
+
+    /// Also publish Home Assistant MQTT discovery configs below `prefix`
+    /// (`homeassistant` unless changed in Home Assistant).
+    ///
+    /// Areas map to `alarm_control_panel` entities with [`Mode::Disarmed`],
+    /// [`Mode::Armed`], [`Mode::Home1`], [`Mode::Home2`] and [`Mode::Home3`]
+    /// shown as disarmed, armed away, armed home, armed night and armed
+    /// vacation, and any other mode as unknown. Devices map to a `switch` (switches and smart plugs) or a
+    /// `binary_sensor` (contacts and detectors), a `sensor` per reading and
+    /// diagnostic battery, tamper and condition `binary_sensor`s.
+    pub fn home_assistant(mut self, prefix: impl Into<String>) -> Bridge {
+        self.discovery_prefix = Some(prefix.into().trim_end_matches('/').to_owned());
+        self
+    }
+
+    /// Run the bridge.
+    ///
+    /// Fails if the first connection to the broker cannot be established.
+    /// Afterwards, lost connections are re-established and errors of the
+    /// panel are published to the `error` topic, so the bridge keeps running.
+    pub async fn run(self) -> Result {
+        let mut options = self.options.clone();
+        options.set_last_will(LastWill::new(
+            self.topic("status"),
+            OFFLINE,
+            QoS::AtLeastOnce,
+            true,
+        ));
+
+        let (mqtt, eventloop) = AsyncClient::new(options, 64);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let _poller = AbortOnDrop(tokio::spawn(poll(eventloop, tx)));
+
+        let mut ticker = tokio::time::interval(self.interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        // Commands run on their own tasks, so a slow panel does not hold up
+        // the event loop; they are aborted once the bridge returns.
+        let mut commands = JoinSet::new();
+        let mut published = HashMap::new();
+        let mut connected = false;
+        let mut first_connection = true;
+
+        loop {
+            tokio::select! {
+                packet = rx.recv() => match packet {
+                    Some(Ok(Packet::ConnAck(_))) => {
+                        connected = true;
+                        first_connection = false;
+                        // Publish everything again, but keep the topics so
+                        // that those of removed devices are still cleared
+                        published.values_mut().for_each(String::clear);
+                        mqtt.subscribe(self.topic("+/+/+/set"), QoS::AtLeastOnce).await?;
+                        mqtt.publish(self.topic("status"), QoS::AtLeastOnce, true, ONLINE)
+                            .await?;
+                        ticker.reset_immediately();
+                    }
+                    Some(Ok(Packet::Publish(publish))) => {
+                        let payload = String::from_utf8_lossy(&publish.payload);
+                        match self.parse_command(&publish.topic, &payload) {
+                            Ok(command) => {
+                                commands.spawn(execute(self.client.clone(), command));
+                            }
+                            Err(e) => self.publish_error(&mqtt, &e).await?,
+                        }
+                    }
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) if first_connection => return Err(e.into()),
+                    Some(Err(_)) => connected = false,
+                    None => return Ok(()),
+                },
+
+                Some(done) = commands.join_next() => match done {
+                    Ok(Ok(())) => ticker.reset_immediately(),
+                    Ok(Err(e)) => self.publish_error(&mqtt, &e).await?,
+                    Err(e) => std::panic::resume_unwind(e.into_panic()),
+                },
+
+                _ = ticker.tick(), if connected => {
+                    let snapshot =
+                        futures::try_join!(self.client.get_status(), self.client.list_devices());
+                    let (modes, devices) = match snapshot {
+                        Ok(snapshot) => snapshot,
+                        Err(e) => {
+                            self.publish_error(&mqtt, &e).await?;
+                            continue;
+                        }
+                    };
+
+                    let messages = self.state_messages(&modes, &devices);
+                    for (topic, payload) in updates(&mut published, messages) {
+                        mqtt.publish(topic, QoS::AtLeastOnce, true, payload).await?;
+                    }
+                }
+            }
+        }
+    }
+
+    fn topic(&self, suffix: &str) -> String {
+        format!("{}/{suffix}", self.base_topic)
+    }
+
+    async fn publish_error(&self, mqtt: &AsyncClient, error: &Error) -> Result {
+        let message = error.to_string();
+        mqtt.publish(self.topic("error"), QoS::AtLeastOnce, false, message)
+            .await?;
+        Ok(())
+    }
+
+    /// The retained state topics and their payloads for a snapshot of the
+    /// panel.
+    fn state_messages(&self, modes: &Modes, devices: &[Device]) -> Vec<(String, String)> {
+        let mut messages: Vec<_> = modes
+            .areas
+            .iter()
+            .map(|condition| {
+                let area = u8::from(condition.area);
+                (
+                    self.topic(&format!("area/{area}/mode")),
+                    condition.mode.to_string(),
+                )
+            })
+            .collect();
+
+        for device in devices {
+            let topic = |field: &str| self.topic(&format!("device/{}/{field}", device.sid));
+
+            messages.push((topic("state"), device.state.to_string()));
+            messages.push((topic("battery"), device.battery.to_string()));
+            messages.push((topic("tamper"), device.tamper.to_string()));
+            messages.push((topic("condition"), device.condition.to_string()));
+
+            if device.kind.is_switch() {
+                let on = device.state == State::Open;
+                messages.push((topic("switch"), if on { "on" } else { "off" }.to_owned()));
+            }
+
+            for (quantity, value) in device.readings.iter() {
+                let quantity = quantity.to_string().to_ascii_lowercase();
+                messages.push((topic(&quantity), value.to_string()));
+            }
+        }
+
+        if let Some(prefix) = &self.discovery_prefix {
+            let discovery = Discovery {
+                prefix,
+                base_topic: &self.base_topic,
+            };
+            let configs = modes
+                .areas
+                .iter()
+                .map(|condition| discovery.area(condition.area))
+                .chain(devices.iter().flat_map(|device| discovery.device(device)));
+
+            messages.extend(configs.map(|(topic, config)| (topic, config.to_string())));
+        }
+
+        messages
+    }
+
+    /// Parse a message received on a command topic.
+    fn parse_command(&self, topic: &str, payload: &str) -> Result<Command> {
+        let invalid = || Error::InvalidArgument(format!("invalid command topic: {topic}"));
+        let payload = payload.trim();
+
+        let path = topic
+            .strip_prefix(&self.base_topic)
+            .and_then(|path| path.strip_prefix('/'))
+            .and_then(|path| path.strip_suffix("/set"))
+            .ok_or_else(invalid)?;
+
+        let (id, field) = match path.split('/').collect::<Vec<_>>()[..] {
+            ["area", area, "mode"] => {
+                let mode = payload
+                    .parse()
+                    .map_err(|_| Error::InvalidArgument(format!("invalid mode: {payload}")))?;
+                return Ok(Command::Mode(area.parse()?, mode));
+            }
+            ["device", sid, field] => (sid.to_owned(), field),
+            _ => return Err(invalid()),
+        };
+
+        let argument = |what: &str| Error::InvalidArgument(format!("invalid {what}: {payload}"));
+
+        match field {
+            "switch" => match payload.to_ascii_lowercase().as_str() {
+                "on" => Ok(Command::Switch(id, true)),
+                "off" => Ok(Command::Switch(id, false)),
+                _ => Err(argument("switch state")),
+            },
+            "target_temperature" => payload
+                .parse()
+                .map(|celsius| Command::TargetTemperature(id, celsius))
+                .map_err(|_| argument("target temperature")),
+            "thermostat_mode" => payload
+                .parse()
+                .map(|mode| Command::ThermostatMode(id, mode))
+                .map_err(|_| argument("thermostat mode")),
+            "shutter" => Ok(Command::Shutter(id, payload.parse()?)),
+            "level" => payload
+                .parse()
+                .map(|level| Command::Level(id, level))
+                .map_err(|_| argument("level")),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// The retained messages to publish for a new snapshot of the panel: those
+/// whose payload changed since the last one, and an empty payload, which
+/// deletes the retained message, for each topic that is gone, e.g. of a
+/// removed device or its discovery configs.
+fn updates(
+    published: &mut HashMap<String, String>,
+    messages: Vec<(String, String)>,
+) -> Vec<(String, String)> {
+    let current: HashMap<_, _> = messages.into_iter().collect();
+
+    let mut removed: Vec<_> = published
+        .keys()
+        .filter(|topic| !current.contains_key(*topic))
+        .cloned()
+        .collect();
+    removed.sort();
+    for topic in &removed {
+        published.remove(topic);
+    }
+
+    let mut changed: Vec<_> = current
+        .into_iter()
+        .filter(|(topic, payload)| published.get(topic) != Some(payload))
+        .collect();
+    changed.sort();
+    for (topic, payload) in &changed {
+        published.insert(topic.clone(), payload.clone());
+    }
+
+    changed
+        .into_iter()
+        .chain(removed.into_iter().map(|topic| (topic, String::new())))
+        .collect()
+}
+
+/// Run a command received on a `…/set` topic.
+async fn execute(client: Client, command: Command) -> Result {
+    match command {
+        Command::Mode(area, mode) => client.change_mode(area, mode).await,
+        Command::Switch(sid, on) => client.set_switch(&sid, on).await,
+        Command::TargetTemperature(sid, celsius) => {
+            client.set_target_temperature(&sid, celsius).await
+        }
+        Command::ThermostatMode(sid, mode) => client.set_thermostat_mode(&sid, mode).await,
+        Command::Shutter(sid, command) => client.move_shutter(&sid, command).await,
+        Command::Level(sid, level) => client.set_dimmer_level(&sid, level).await,
+    }
+}
+
+/// Drive the MQTT event loop and forward incoming packets and connection
+/// errors to the bridge.
+async fn poll(
+    mut eventloop: EventLoop,
+    tx: mpsc::UnboundedSender<std::result::Result<Packet, ConnectionError>>,
+) {
+    loop {
+        let packet = match eventloop.poll().await {
+            Ok(rumqttc::Event::Incoming(packet)) => Ok(packet),
+            Ok(rumqttc::Event::Outgoing(_)) => continue,
+            Err(e) => Err(e),
+        };
+
+        let failed = packet.is_err();
+        if tx.send(packet).is_err() {
+            return;
+        }
+        if failed {
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    }
+}
+
+/// Stops the event loop task once the bridge returns.
+struct AbortOnDrop(JoinHandle<()>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::tests::{builder, mount_token};
+
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn bridge() -> Bridge {
+        let client = Client::builder("user", "pass")
+            .base_url("http://127.0.0.1:1/action/")
+            .build()
+            .unwrap();
+        Bridge::new(client, MqttOptions::new("test", "localhost", 1883)).base_topic("home/alarm/")
+    }
+
+    #[test]
+    fn state_topics() {
+        use crate::resources::ApiResponse;
+
+        let modes = serde_json::from_value::<crate::resources::panel::Condition>(
+            serde_json::json!({ "forms": { "pcondform1": { "mode": 1 } } }),
+        )
+        .unwrap()
+        .into_result()
+        .unwrap();
+        let devices: Vec<Device> = serde_json::from_value(serde_json::json!([
+            {
+                "sid": "ZS:01", "type": 24, "name": "Lamp", "area": 1,
+                "status_ex": 1, "cond_ok": 1, "battery_ok": 0, "tamper_ok": 1,
+                "temperature": "21.5"
+            }
+        ]))
+        .unwrap();
+
+        let messages: HashMap<_, _> = bridge()
+            .state_messages(&modes, &devices)
+            .into_iter()
+            .collect();
+
+        assert_eq!(messages["home/alarm/area/1/mode"], "Armed");
+        assert_eq!(messages["home/alarm/device/ZS:01/state"], "Open");
+        assert_eq!(messages["home/alarm/device/ZS:01/battery"], "Error");
+        assert_eq!(messages["home/alarm/device/ZS:01/switch"], "on");
+        assert_eq!(messages["home/alarm/device/ZS:01/temperature"], "21.5");
+        assert_eq!(messages.len(), 7);
+
+        let messages = bridge()
+            .home_assistant("homeassistant")
+            .state_messages(&modes, &devices);
+        let topics: Vec<_> = messages
+            .iter()
+            .map(|(topic, _)| topic.as_str())
+            .filter(|topic| topic.starts_with("homeassistant/"))
+            .collect();
+        assert_eq!(
+            topics,
+            [
+                "homeassistant/alarm_control_panel/home_alarm/area_1/config",
+                "homeassistant/switch/home_alarm/ZS_01_switch/config",
+                "homeassistant/sensor/home_alarm/ZS_01_temperature/config",
+                "homeassistant/binary_sensor/home_alarm/ZS_01_battery/config",
+                "homeassistant/binary_sensor/home_alarm/ZS_01_tamper/config",
+                "homeassistant/binary_sensor/home_alarm/ZS_01_condition/config",
+            ]
+        );
+    }
+
+    #[test]
+    fn removed_devices_are_cleared() {
+        let messages = |sids: &[&str]| {
+            sids.iter()
+                .flat_map(|sid| {
+                    [
+                        (
+                            format!("home/alarm/device/{sid}/state"),
+                            "Closed".to_string(),
+                        ),
+                        (format!("home/alarm/device/{sid}/battery"), "Ok".to_string()),
+                    ]
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut published = HashMap::new();
+
+        assert_eq!(
+            updates(&mut published, messages(&["RF:01", "RF:02"])).len(),
+            4
+        );
+        assert_eq!(
+            updates(&mut published, messages(&["RF:01"])),
+            [
+                ("home/alarm/device/RF:02/battery".to_string(), String::new()),
+                ("home/alarm/device/RF:02/state".to_string(), String::new()),
+            ]
+        );
+        assert!(updates(&mut published, messages(&["RF:01"])).is_empty());
+
+        // After a reconnect everything is published again, and devices
+        // removed meanwhile are still cleared
+        published.values_mut().for_each(String::clear);
+        let republished = updates(&mut published, Vec::new());
+        assert_eq!(republished.len(), 2);
+        assert!(republished.iter().all(|(_, payload)| payload.is_empty()));
+        assert!(published.is_empty());
+    }
+
+    #[test]
+    fn parse_commands() {
+        let bridge = bridge();
+
+        assert_eq!(
+            bridge
+                .parse_command("home/alarm/area/2/mode/set", "ARMED")
+                .unwrap(),
+            Command::Mode(Area::Area2, Mode::Armed)
+        );
+        assert_eq!(
+            bridge
+                .parse_command("home/alarm/device/ZS:01/switch/set", "on")
+                .unwrap(),
+            Command::Switch("ZS:01".into(), true)
+        );
+        assert_eq!(
+            bridge
+                .parse_command("home/alarm/device/ZS:02/target_temperature/set", "21.5")
+                .unwrap(),
+            Command::TargetTemperature("ZS:02".into(), 21.5)
+        );
+        assert_eq!(
+            bridge
+                .parse_command("home/alarm/device/ZS:02/thermostat_mode/set", "auto")
+                .unwrap(),
+            Command::ThermostatMode("ZS:02".into(), ThermostatMode::Auto)
+        );
+        assert_eq!(
+            bridge
+                .parse_command("home/alarm/device/ZS:03/shutter/set", "40%")
+                .unwrap(),
+            Command::Shutter("ZS:03".into(), ShutterCommand::Position(40))
+        );
+        assert_eq!(
+            bridge
+                .parse_command("home/alarm/device/ZS:04/level/set", "75")
+                .unwrap(),
+            Command::Level("ZS:04".into(), 75)
+        );
+
+        for (topic, payload) in [
+            ("home/alarm/area/1/mode/set", "panic"),
+            ("home/alarm/device/ZS:01/switch/set", "toggle"),
+            ("home/alarm/device/ZS:01/colour/set", "red"),
+            ("home/alarm/area/1/mode", "armed"),
+            ("other/area/1/mode/set", "armed"),
+        ] {
+            assert!(
+                matches!(
+                    bridge.parse_command(topic, payload),
+                    Err(Error::InvalidArgument(_))
+                ),
+                "{topic} {payload}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn unreachable_broker_fails() {
+        let client = Client::builder("user", "pass")
+            .base_url("http://127.0.0.1:1/action/")
+            .build()
+            .unwrap();
+        let result = Bridge::new(client, MqttOptions::new("test", "127.0.0.1", 1))
+            .run()
+            .await;
+        assert!(matches!(result, Err(Error::Mqtt(_))));
+    }
+
+    /// Start an MQTT broker on a free local port and wait until it accepts
+    /// connections.
+    fn start_broker() -> u16 {
+        let listen = std::net::TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .unwrap();
+
+        let server = rumqttd::ServerSettings {
+            name: "test".to_owned(),
+            listen,
+            tls: None,
+            next_connection_delay_ms: 1,
+            connections: rumqttd::ConnectionSettings {
+                connection_timeout_ms: 5000,
+                max_payload_size: 64 * 1024,
+                max_inflight_count: 100,
+                auth: None,
+                external_auth: None,
+                dynamic_filters: false,
+            },
+        };
+        let config = rumqttd::Config {
+            router: rumqttd::RouterConfig {
+                max_connections: 10,
+                max_outgoing_packet_count: 200,
+                max_segment_size: 1024 * 1024,
+                max_segment_count: 10,
+                ..Default::default()
+            },
+            v4: Some(HashMap::from([("test".to_owned(), server)])),
+            ..Default::default()
+        };
+        std::thread::spawn(move || rumqttd::Broker::new(config).start().unwrap());
+
+        while std::net::TcpStream::connect(listen).is_err() {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        listen.port()
+    }
+
+    #[tokio::test]
+    async fn bridge_with_broker() {
+        let (host, port) = ("127.0.0.1", start_broker());
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/action/panelCondGet"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({ "forms": { "pcondform1": { "mode": 0 } } })),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/action/deviceListGet"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "senrows": []
+            })))
+            .mount(&server)
+            .await;
+        mount_token(&server).await;
+        Mock::given(method("POST"))
+            .and(path("/action/panelCondPost"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "result": 1,
+                "message": "OK"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = builder(&server).build().unwrap();
+        let base_topic = "alarmate-test";
+        let bridge = Bridge::new(client, MqttOptions::new("alarmate-bridge", host, port))
+            .base_topic(base_topic)
+            .interval(Duration::from_millis(100));
+        let bridge = AbortOnDrop(tokio::spawn(async move {
+            bridge.run().await.unwrap();
+        }));
+
+        let (observer, mut eventloop) =
+            AsyncClient::new(MqttOptions::new("alarmate-observer", host, port), 16);
+        observer
+            .subscribe(format!("{base_topic}/area/1/mode"), QoS::AtLeastOnce)
+            .await
+            .unwrap();
+
+        let mode = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                if let rumqttc::Event::Incoming(Packet::Publish(publish)) =
+                    eventloop.poll().await.unwrap()
+                {
+                    return String::from_utf8(publish.payload.to_vec()).unwrap();
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(mode, "Disarmed");
+
+        observer
+            .publish(
+                format!("{base_topic}/area/1/mode/set"),
+                QoS::AtLeastOnce,
+                false,
+                "armed",
+            )
+            .await
+            .unwrap();
+
+        tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                let requests = server.received_requests().await.unwrap();
+                if requests
+                    .iter()
+                    .any(|r| r.url.path() == "/action/panelCondPost")
+                {
+                    return;
+                }
+                tokio::select! {
+                    _ = eventloop.poll() => {}
+                    () = tokio::time::sleep(Duration::from_millis(50)) => {}
+                }
+            }
+        })
+        .await
+        .unwrap();
+
+        drop(bridge);
+    }
+}
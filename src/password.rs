@@ -0,0 +1,52 @@
+use zeroize::Zeroizing;
+
+use std::fmt;
+
+/// A password that is wiped from memory when dropped and never shows up in
+/// `Debug` output.
+///
+/// Create one from a `String` or `&str`:
+///
+/// ```rust
+/// let password = alarmate::Password::from("changeme");
+/// assert_eq!(format!("{password:?}"), "Password(***)");
+/// ```
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Password(Zeroizing<String>);
+
+impl Password {
+    /// The password in plain text.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for Password {
+    fn from(password: String) -> Password {
+        Password(Zeroizing::new(password))
+    }
+}
+
+impl From<&str> for Password {
+    fn from(password: &str) -> Password {
+        Password::from(password.to_owned())
+    }
+}
+
+impl fmt::Debug for Password {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Password(***)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug_is_redacted() {
+        let password = Password::from(String::from("hunter2"));
+        assert_eq!(password.expose(), "hunter2");
+        assert!(!format!("{password:?}").contains("hunter2"));
+    }
+}
@@ -0,0 +1,231 @@
+use serde::{Deserialize, Serialize};
+
+use std::fmt;
+
+use crate::constants::{Area, DeviceKind, State, Status};
+use crate::resources::devices::Device;
+
+/// Which devices of an area stand in the way of arming it, as reported by
+/// [`Client::arm_readiness`](crate::Client::arm_readiness)
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ArmReadiness {
+    /// The area
+    pub area: Area,
+
+    /// One entry per problem, so a device can be listed more than once
+    pub blockers: Vec<Blocker>,
+}
+
+impl ArmReadiness {
+    /// Collect the blockers of `area` from the given devices and the
+    /// `deviceListGet` rows that could not be parsed.
+    pub(crate) fn from_devices(
+        area: Area,
+        devices: &[Device],
+        malformed: &[serde_json::Value],
+    ) -> ArmReadiness {
+        let mut blockers: Vec<_> = devices
+            .iter()
+            .filter(|device| device.area == area)
+            .flat_map(|device| {
+                let statuses = [device.condition, device.battery, device.tamper];
+                let unreadable = (device.kind.is_contact()
+                    && matches!(device.state, State::Unknown(_)))
+                    || statuses
+                        .iter()
+                        .any(|status| matches!(status, Status::Unknown(_)));
+
+                [
+                    (device.kind.is_contact() && device.state == State::Open)
+                        .then_some(Problem::Open),
+                    (device.condition == Status::Error).then_some(Problem::Fault),
+                    (device.battery == Status::Error).then_some(Problem::LowBattery),
+                    (device.tamper == Status::Error).then_some(Problem::Tampered),
+                    unreadable.then_some(Problem::Unknown),
+                ]
+                .into_iter()
+                .flatten()
+                .map(|problem| Blocker {
+                    sid: device.sid.clone(),
+                    name: device.name.clone(),
+                    kind: Some(device.kind),
+                    problem,
+                })
+            })
+            .collect();
+
+        // A row that cannot be parsed may describe an open or faulty device
+        // of the area; it only does not block if it names another area.
+        blockers.extend(malformed.iter().filter_map(|row| {
+            let field = |name: &str| row.get(name).filter(|value| !value.is_null());
+            let text = |name: &str| {
+                field(name)
+                    .and_then(|value| String::deserialize(value).ok())
+                    .unwrap_or_default()
+            };
+
+            let row_area = field("area").and_then(|value| Area::deserialize(value).ok());
+            (row_area.is_none_or(|row_area| row_area == area)).then(|| Blocker {
+                sid: text("sid"),
+                name: text("name"),
+                kind: field("type").and_then(|value| DeviceKind::deserialize(value).ok()),
+                problem: Problem::Unknown,
+            })
+        }));
+
+        ArmReadiness { area, blockers }
+    }
+
+    /// Whether nothing stands in the way of arming the area.
+    #[must_use]
+    pub fn is_ready(&self) -> bool {
+        self.blockers.is_empty()
+    }
+}
+
+impl fmt::Display for ArmReadiness {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_ready() {
+            return write!(f, "{} is ready to arm", self.area);
+        }
+
+        write!(f, "{} is not ready to arm: ", self.area)?;
+        for (i, blocker) in self.blockers.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{} ({}) {}", blocker.name, blocker.sid, blocker.problem)?;
+        }
+        Ok(())
+    }
+}
+
+/// A device problem that stands in the way of arming
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Blocker {
+    /// The sensor ID
+    pub sid: String,
+    /// The device name
+    pub name: String,
+    /// The device kind, if the panel reported one that could be read
+    pub kind: Option<DeviceKind>,
+    /// What is wrong with the device
+    pub problem: Problem,
+}
+
+/// What is wrong with a device that stands in the way of arming
+///
+/// A device whose state cannot be read blocks arming as well
+/// ([`Problem::Unknown`]), so an empty list of blockers means that every
+/// device of the area was checked.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Problem {
+    /// A door or window contact is open
+    Open,
+    /// The panel reports a fault (`condition` is [`Status::Error`])
+    Fault,
+    /// The battery is low (`battery` is [`Status::Error`])
+    LowBattery,
+    /// The device was tampered with (`tamper` is [`Status::Error`])
+    Tampered,
+    /// The state of a contact, the condition, the battery or the tamper
+    /// status is unknown to the crate, or the panel reported the device in a
+    /// form that could not be read at all. The device may be open or faulty.
+    Unknown,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Problem::Open => "is open",
+            Problem::Fault => "reports a fault",
+            Problem::LowBattery => "has a low battery",
+            Problem::Tampered => "was tampered with",
+            Problem::Unknown => "cannot be checked",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(sid: &str, kind: u8, area: u8, state: u8, battery: u8) -> Device {
+        serde_json::from_value(serde_json::json!({
+            "sid": sid,
+            "type": kind,
+            "name": sid,
+            "area": area,
+            "status_ex": state,
+            "cond_ok": 1,
+            "battery_ok": battery,
+            "tamper_ok": 1
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn blockers_of_area() {
+        let devices = [
+            // open door contact with a low battery
+            device("RF:01", 4, 1, 1, 0),
+            // closed door contact
+            device("RF:02", 4, 1, 0, 1),
+            // "open" motion detector does not block
+            device("RF:03", 9, 1, 1, 1),
+            // open door contact in another area
+            device("RF:04", 4, 2, 1, 1),
+        ];
+
+        let readiness = ArmReadiness::from_devices(Area::Area1, &devices, &[]);
+        assert!(!readiness.is_ready());
+        let problems: Vec<_> = readiness
+            .blockers
+            .iter()
+            .map(|blocker| (blocker.sid.as_str(), blocker.problem))
+            .collect();
+        assert_eq!(
+            problems,
+            [("RF:01", Problem::Open), ("RF:01", Problem::LowBattery)]
+        );
+        assert_eq!(
+            readiness.to_string(),
+            "Area1 is not ready to arm: RF:01 (RF:01) is open, RF:01 (RF:01) has a low battery"
+        );
+
+        assert!(ArmReadiness::from_devices(Area::Other(3), &devices, &[]).is_ready());
+    }
+
+    #[test]
+    fn unreadable_devices_block() {
+        let devices = [
+            // door contact in a state the crate does not know
+            device("RF:01", 4, 1, 7, 1),
+            // motion detector with an unknown battery status
+            device("RF:02", 9, 1, 0, 2),
+            // unknown state of a motion detector does not matter
+            device("RF:03", 9, 1, 7, 1),
+        ];
+        let malformed = [
+            serde_json::json!({ "sid": "RF:04", "type": 4, "name": "Back Door", "area": 1 }),
+            serde_json::json!({ "sid": "RF:05", "type": "door" }),
+            serde_json::json!({ "sid": "RF:06", "type": 4, "area": 2 }),
+        ];
+
+        let readiness = ArmReadiness::from_devices(Area::Area1, &devices, &malformed);
+        let problems: Vec<_> = readiness
+            .blockers
+            .iter()
+            .map(|blocker| (blocker.sid.as_str(), blocker.kind, blocker.problem))
+            .collect();
+        assert_eq!(
+            problems,
+            [
+                ("RF:01", Some(DeviceKind::DoorContact), Problem::Unknown),
+                ("RF:02", Some(DeviceKind::MotionDetector), Problem::Unknown),
+                ("RF:04", Some(DeviceKind::DoorContact), Problem::Unknown),
+                ("RF:05", None, Problem::Unknown),
+            ]
+        );
+    }
+}
@@ -0,0 +1,262 @@
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::errors::Result;
+use crate::tls::HttpClient;
+
+/// What replaces the session token in recorded `tokenGet` responses
+const REDACTED: &str = "REDACTED";
+
+/// A panel response with its body read
+pub(crate) struct Reply {
+    pub(crate) status: reqwest::StatusCode,
+    pub(crate) body: String,
+}
+
+impl Reply {
+    pub(crate) async fn read(res: reqwest::Response) -> Result<Reply> {
+        let status = res.status();
+        let body = res.text().await?;
+        Ok(Reply { status, body })
+    }
+}
+
+/// How the client exchanges requests and responses with the panel
+pub(crate) enum Transport {
+    /// Send requests to the panel
+    Http(HttpClient),
+
+    /// Send requests to the panel and append each exchange to a fixture file
+    Record(HttpClient, Recorder),
+
+    /// Answer requests from a fixture file without contacting the panel
+    Replay(Replay),
+}
+
+/// One request to the panel and its response, as stored in a fixture file.
+///
+/// Credentials and tokens are never part of an exchange: the basic auth and
+/// `X-Token` headers are not recorded and the token in `tokenGet` responses
+/// is replaced.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct Exchange {
+    method: String,
+    action: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    form: Option<BTreeMap<String, String>>,
+    status: u16,
+    body: String,
+}
+
+/// The form fields of a request, e.g. `[("mode", 1), ("area", 2)]`, as
+/// strings by name.
+pub(crate) fn form_fields<T: Serialize + ?Sized>(form: &T) -> Result<BTreeMap<String, String>> {
+    let pairs: Vec<(String, serde_json::Value)> =
+        serde_json::from_value(serde_json::to_value(form)?)?;
+
+    Ok(pairs
+        .into_iter()
+        .map(|(name, value)| match value {
+            serde_json::Value::String(text) => (name, text),
+            other => (name, other.to_string()),
+        })
+        .collect())
+}
+
+/// Appends exchanges to a fixture file, one JSON object per line
+pub(crate) struct Recorder {
+    file: Mutex<File>,
+}
+
+impl Recorder {
+    pub(crate) fn create(path: &Path) -> Result<Recorder> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Recorder {
+            file: Mutex::new(file),
+        })
+    }
+
+    pub(crate) fn record(
+        &self,
+        method: &str,
+        action: &str,
+        form: Option<BTreeMap<String, String>>,
+        reply: &Reply,
+    ) -> Result {
+        let exchange = Exchange {
+            method: method.into(),
+            action: action.into(),
+            form,
+            status: reply.status.as_u16(),
+            body: redact(action, &reply.body),
+        };
+
+        let mut line = serde_json::to_string(&exchange)?;
+        line.push('\n');
+
+        let mut file = self.file.lock().expect("recorder lock poisoned");
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Replace the session token in a `tokenGet` response.
+fn redact(action: &str, body: &str) -> String {
+    if action != "tokenGet" {
+        return body.to_owned();
+    }
+
+    match serde_json::from_str::<serde_json::Value>(body) {
+        Ok(mut json) if json.get("message").is_some() => {
+            json["message"] = REDACTED.into();
+            json.to_string()
+        }
+        _ => body.to_owned(),
+    }
+}
+
+/// Answers requests with the recorded responses
+///
+/// Each request gets the first unused exchange with the same method, action
+/// and form. Once all of them have been used, the last one is repeated, so
+/// polling keeps seeing the final recorded state.
+pub(crate) struct Replay {
+    exchanges: Vec<Exchange>,
+    used: Mutex<Vec<bool>>,
+}
+
+impl Replay {
+    pub(crate) fn load(path: &Path) -> Result<Replay> {
+        let mut exchanges = Vec::new();
+        for line in BufReader::new(File::open(path)?).lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                exchanges.push(serde_json::from_str(&line)?);
+            }
+        }
+
+        Ok(Replay {
+            used: Mutex::new(vec![false; exchanges.len()]),
+            exchanges,
+        })
+    }
+
+    pub(crate) fn reply(
+        &self,
+        method: &str,
+        action: &str,
+        form: Option<BTreeMap<String, String>>,
+    ) -> Result<Reply> {
+        let mut used = self.used.lock().expect("replay lock poisoned");
+        let matching: Vec<_> = (0..self.exchanges.len())
+            .filter(|&i| {
+                let exchange = &self.exchanges[i];
+                exchange.method == method && exchange.action == action && exchange.form == form
+            })
+            .collect();
+
+        let index = matching
+            .iter()
+            .copied()
+            .find(|&i| !used[i])
+            .or(matching.last().copied())
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("no recorded response to {method} {action}"),
+                )
+            })?;
+        used[index] = true;
+
+        let exchange = &self.exchanges[index];
+        let status = reqwest::StatusCode::from_u16(exchange.status)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        Ok(Reply {
+            status,
+            body: exchange.body.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::tests::builder;
+    use crate::{Area, Client, Error, Mode};
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn tokens_are_redacted() {
+        let body = r#"{"result": 1, "message": "secret-token"}"#;
+        assert!(!redact("tokenGet", body).contains("secret-token"));
+        assert_eq!(redact("panelCondGet", body), body);
+        assert_eq!(redact("tokenGet", "<html>"), "<html>");
+    }
+
+    #[test]
+    fn form_fields_as_strings() {
+        let fields = form_fields(&[("mode", 1), ("area", 2)]).unwrap();
+        assert_eq!(fields["mode"], "1");
+        assert_eq!(fields["area"], "2");
+    }
+
+    #[tokio::test]
+    async fn record_and_replay() {
+        let server = MockServer::start().await;
+        let fixture =
+            std::env::temp_dir().join(format!("alarmate-fixture-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&fixture);
+
+        Mock::given(method("GET"))
+            .and(path("/action/tokenGet"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({"result": 1, "message": "secret-token"})),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/action/panelCondGet"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                "{\"forms\": {\"pcondform1\": {\"mode\": \"1\",\t\"alarm\": \"0\"}}}",
+            ))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/action/panelCondPost"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({"result": 1, "message": ""})),
+            )
+            .mount(&server)
+            .await;
+
+        let client = builder(&server).record(&fixture).build().unwrap();
+        client.change_mode(Area::Area1, Mode::Armed).await.unwrap();
+        let recorded = client.get_status().await.unwrap();
+
+        let text = std::fs::read_to_string(&fixture).unwrap();
+        assert_eq!(text.lines().count(), 3);
+        assert!(!text.contains("secret-token"));
+        assert!(!text.contains("pass"));
+
+        let client = Client::builder("user", "pass")
+            .replay(&fixture)
+            .build()
+            .unwrap();
+        client.change_mode(Area::Area1, Mode::Armed).await.unwrap();
+        assert_eq!(client.get_status().await.unwrap(), recorded);
+        assert_eq!(client.get_status().await.unwrap(), recorded);
+
+        let result = client.change_mode(Area::Area2, Mode::Armed).await;
+        std::fs::remove_file(&fixture).unwrap();
+        assert!(matches!(result.unwrap_err(), Error::Io(_)));
+    }
+}
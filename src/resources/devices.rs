@@ -1,8 +1,9 @@
-use serde::{Deserialize, Serialize};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Serialize, Serializer};
 
 use crate::constants::{Area, DeviceKind, State, Status};
 use crate::errors::Result;
-use crate::resources::ApiResponse;
+use crate::resources::{ApiResponse, parse_rows, partition_rows, readings::Readings};
 
 /// Holds information about a Lupusec Alarm / Smarthome device
 #[derive(Serialize, Deserialize, Debug)]
@@ -10,37 +11,76 @@ pub struct Device {
     /// The sensor ID
     pub sid: String,
     /// The device kind
-    #[serde(rename = "type")]
+    #[serde(rename(deserialize = "type"))]
     pub kind: DeviceKind,
     /// The device name
     pub name: String,
     /// The area the device belongs to
     pub area: Area,
     /// The device state
-    #[serde(rename = "status_ex")]
+    #[serde(rename(deserialize = "status_ex"))]
     pub state: State,
     /// The device condition
-    #[serde(rename = "cond_ok")]
+    #[serde(rename(deserialize = "cond_ok"))]
     pub condition: Status,
     /// The battery status
-    #[serde(rename = "battery_ok")]
+    #[serde(rename(deserialize = "battery_ok"))]
     pub battery: Status,
     /// The tamper status
-    #[serde(rename = "tamper_ok")]
+    #[serde(rename(deserialize = "tamper_ok"))]
     pub tamper: Status,
+    /// Measurements reported by sensors and meters
+    #[serde(flatten)]
+    pub readings: Readings,
+    /// All other fields reported by the panel, as raw JSON
+    #[serde(
+        flatten,
+        serialize_with = "serialize_extra",
+        skip_serializing_if = "serde_json::Map::is_empty"
+    )]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Serialize the unmapped fields nested below `extra`, so that the top-level
+/// fields of a serialized `Device` do not depend on the panel firmware.
+fn serialize_extra<S: Serializer>(
+    extra: &serde_json::Map<String, serde_json::Value>,
+    s: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    let mut map = s.serialize_map(Some(1))?;
+    map.serialize_entry("extra", extra)?;
+    map.end()
 }
 
 #[derive(Deserialize)]
 pub(crate) struct List {
     #[serde(rename = "senrows")]
-    list: Vec<Device>,
+    list: Vec<serde_json::Value>,
 }
 
 impl ApiResponse for List {
     type Type = Vec<Device>;
 
+    /// Rows that cannot be parsed are skipped.
     fn into_result(self) -> Result<Self::Type> {
-        Ok(self.list)
+        Ok(parse_rows(self.list))
+    }
+}
+
+/// The `deviceListGet` rows, keeping the rows that cannot be parsed for
+/// checks that must not overlook a device.
+#[derive(Deserialize)]
+pub(crate) struct Rows {
+    #[serde(rename = "senrows")]
+    list: Vec<serde_json::Value>,
+}
+
+impl ApiResponse for Rows {
+    /// The parsed devices and the malformed rows
+    type Type = (Vec<Device>, Vec<serde_json::Value>);
+
+    fn into_result(self) -> Result<Self::Type> {
+        Ok(partition_rows(self.list))
     }
 }
 
@@ -71,18 +111,69 @@ mod tests {
     }
 
     #[test]
-    fn unknown_device_type_fails() {
+    fn deserialize_sensor_readings() {
+        let mut json = sample_device_json();
+        json["type"] = 54.into();
+        json["temperature"] = "19.5".into();
+        json["humidity"] = "61".into();
+
+        let device: Device = serde_json::from_value(json).unwrap();
+        assert_eq!(device.kind, DeviceKind::RoomSensor);
+        assert_eq!(device.readings.temperature, Some(19.5));
+        assert_eq!(device.readings.humidity, Some(61.0));
+        assert_eq!(device.readings.power, None);
+    }
+
+    #[test]
+    fn unknown_device_type_is_kept() {
         let json = serde_json::json!({
             "sid": "RF:002",
-            "type": 999,
+            "type": 99,
             "name": "Unknown",
             "area": 1,
-            "status_ex": 0,
+            "status_ex": 3,
             "cond_ok": 1,
             "battery_ok": 1,
-            "tamper_ok": 1
+            "tamper_ok": 1,
+            "rssi": "7",
+            "temperature": "20"
         });
-        assert!(serde_json::from_value::<Device>(json).is_err());
+        let device: Device = serde_json::from_value(json).unwrap();
+        assert_eq!(device.kind, DeviceKind::Unknown(99));
+        assert_eq!(device.state, State::Unknown(3));
+        assert_eq!(device.readings.temperature, Some(20.0));
+        assert_eq!(device.extra.len(), 1);
+        assert_eq!(device.extra["rssi"], "7");
+    }
+
+    #[test]
+    fn known_fields_are_not_extra() {
+        let device: Device = serde_json::from_value(sample_device_json()).unwrap();
+        assert!(device.extra.is_empty());
+    }
+
+    #[test]
+    fn serialize_with_stable_field_names() {
+        let mut json = sample_device_json();
+        json["temperature"] = "19.5".into();
+        json["rssi"] = "7".into();
+
+        let device: Device = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            serde_json::to_value(&device).unwrap(),
+            serde_json::json!({
+                "sid": "RF:001",
+                "kind": "DoorContact",
+                "name": "Front Door",
+                "area": "Area1",
+                "state": "Closed",
+                "condition": "Ok",
+                "battery": "Ok",
+                "tamper": "Ok",
+                "temperature": 19.5,
+                "extra": { "rssi": "7" }
+            })
+        );
     }
 
     #[test]
@@ -91,4 +182,22 @@ mod tests {
         let list: List = serde_json::from_value(json).unwrap();
         assert!(list.into_result().unwrap().is_empty());
     }
+
+    #[test]
+    fn list_skips_malformed_rows() {
+        let mut unknown_status = sample_device_json();
+        unknown_status["sid"] = "RF:003".into();
+        unknown_status["battery_ok"] = 2.into();
+
+        let json = serde_json::json!({ "senrows": [
+            sample_device_json(),
+            { "sid": "RF:002", "type": 4, "name": "Window" },
+            unknown_status,
+        ] });
+        let list: List = serde_json::from_value(json).unwrap();
+        let devices = list.into_result().unwrap();
+        assert_eq!(devices.len(), 2);
+        assert_eq!(devices[0].sid, "RF:001");
+        assert_eq!(devices[1].battery, Status::Unknown(2));
+    }
 }
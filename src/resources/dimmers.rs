@@ -0,0 +1,48 @@
+use serde::{Deserialize, Serialize};
+
+use crate::constants::DeviceKind;
+use crate::resources::OfKind;
+use crate::utils::deserialize_optional_number;
+
+/// The state of a dimmer
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Dimmer {
+    /// The sensor ID
+    pub sid: String,
+    /// The device kind
+    #[serde(rename(deserialize = "type"))]
+    pub kind: DeviceKind,
+    /// The device name
+    pub name: String,
+    /// The brightness in percent (0 = off)
+    #[serde(default, deserialize_with = "deserialize_optional_number")]
+    pub level: Option<u8>,
+}
+
+impl OfKind for Dimmer {
+    fn of_kind(kind: DeviceKind) -> bool {
+        kind.is_dimmer()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::{ApiResponse, KindList};
+
+    #[test]
+    fn only_dimmers_are_kept() {
+        let json = serde_json::json!({
+            "senrows": [
+                { "sid": "RF:01", "type": 4, "name": "Door", "status_ex": 0 },
+                { "sid": "ZS:02", "type": 53, "name": "Ceiling", "level": 80 },
+                { "sid": "ZS:03", "type": 66, "name": "Desk", "level": "0" }
+            ]
+        });
+        let list: KindList<Dimmer> = serde_json::from_value(json).unwrap();
+        let dimmers = list.into_result().unwrap();
+        assert_eq!(dimmers.len(), 2);
+        assert_eq!(dimmers[0].level, Some(80));
+        assert_eq!(dimmers[1].level, Some(0));
+    }
+}
@@ -0,0 +1,211 @@
+use serde::{Deserialize, Serialize};
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::constants::{Area, EventKind};
+use crate::errors::Result;
+use crate::resources::{ApiResponse, parse_rows};
+use crate::utils::{deserialize_number, deserialize_optional_number, deserialize_optional_string};
+
+/// An entry of the panel's event log
+///
+/// The fields are read from the `d`, `a`, `z`, `sid`, `e` and `u` keys of a
+/// `historyGet` row. Like the codes of [`EventKind`], this mapping has not
+/// been verified against a real panel yet.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct HistoryEntry {
+    /// When the event happened, in seconds since the Unix epoch
+    #[serde(rename(deserialize = "d"), deserialize_with = "deserialize_number")]
+    pub timestamp: u64,
+    /// The area the event belongs to
+    #[serde(rename(deserialize = "a"))]
+    pub area: Area,
+    /// The zone of the device that caused the event
+    #[serde(
+        rename(deserialize = "z"),
+        default,
+        deserialize_with = "deserialize_optional_number"
+    )]
+    pub zone: Option<u8>,
+    /// The sensor ID of the device that caused the event
+    #[serde(default, deserialize_with = "deserialize_optional_string")]
+    pub sid: Option<String>,
+    /// The kind of event
+    #[serde(rename(deserialize = "e"))]
+    pub kind: EventKind,
+    /// The user who caused the event, e.g. by changing the mode
+    #[serde(
+        rename(deserialize = "u"),
+        default,
+        deserialize_with = "deserialize_optional_string"
+    )]
+    pub user: Option<String>,
+}
+
+impl HistoryEntry {
+    /// When the event happened.
+    #[must_use]
+    pub fn time(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.timestamp)
+    }
+}
+
+/// Selects which [`HistoryEntry`] records are returned by
+/// [`Client::history`](crate::Client::history).
+///
+/// ```rust
+/// use std::time::{Duration, SystemTime};
+/// use alarmate::{EventKind, HistoryFilter};
+///
+/// let last_day = HistoryFilter::default()
+///     .since(SystemTime::now() - Duration::from_secs(24 * 60 * 60))
+///     .kind(EventKind::Alarm)
+///     .kind(EventKind::Tamper);
+/// ```
+#[derive(Clone, Debug, Default)]
+#[must_use]
+pub struct HistoryFilter {
+    since: Option<SystemTime>,
+    until: Option<SystemTime>,
+    kinds: Vec<EventKind>,
+}
+
+impl HistoryFilter {
+    /// Only include events at or after `time`.
+    pub fn since(mut self, time: SystemTime) -> HistoryFilter {
+        self.since = Some(time);
+        self
+    }
+
+    /// Only include events before `time`.
+    pub fn until(mut self, time: SystemTime) -> HistoryFilter {
+        self.until = Some(time);
+        self
+    }
+
+    /// Only include events of the given kind. Can be called multiple times
+    /// to include several kinds.
+    pub fn kind(mut self, kind: EventKind) -> HistoryFilter {
+        self.kinds.push(kind);
+        self
+    }
+
+    /// Whether the entry passes the filter.
+    #[must_use]
+    pub fn matches(&self, entry: &HistoryEntry) -> bool {
+        let time = entry.time();
+        self.since.is_none_or(|since| time >= since)
+            && self.until.is_none_or(|until| time < until)
+            && (self.kinds.is_empty() || self.kinds.contains(&entry.kind))
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct History {
+    #[serde(rename = "hisrows")]
+    list: Vec<serde_json::Value>,
+}
+
+impl ApiResponse for History {
+    type Type = Vec<HistoryEntry>;
+
+    /// Rows that cannot be parsed are skipped.
+    fn into_result(self) -> Result<Self::Type> {
+        Ok(parse_rows(self.list))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(timestamp: u64, kind: u8) -> HistoryEntry {
+        serde_json::from_value(serde_json::json!({
+            "d": timestamp,
+            "a": 1,
+            "e": kind,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn deserialize_entry() {
+        let json = serde_json::json!({
+            "d": "1700000000",
+            "a": "2",
+            "z": "4",
+            "sid": "RF:00112233",
+            "e": "1",
+            "u": ""
+        });
+        let entry: HistoryEntry = serde_json::from_value(json).unwrap();
+        assert_eq!(entry.timestamp, 1_700_000_000);
+        assert_eq!(entry.area, Area::Area2);
+        assert_eq!(entry.zone, Some(4));
+        assert_eq!(entry.sid.as_deref(), Some("RF:00112233"));
+        assert_eq!(entry.kind, EventKind::Alarm);
+        assert_eq!(entry.user, None);
+    }
+
+    #[test]
+    fn deserialize_system_entry_without_device() {
+        let entry = entry(1_700_000_000, 3);
+        assert_eq!(entry.kind, EventKind::Disarm);
+        assert_eq!(entry.zone, None);
+        assert_eq!(entry.sid, None);
+    }
+
+    #[test]
+    fn filter_by_time_range() {
+        let filter = HistoryFilter::default()
+            .since(UNIX_EPOCH + Duration::from_secs(100))
+            .until(UNIX_EPOCH + Duration::from_secs(200));
+        assert!(!filter.matches(&entry(99, 1)));
+        assert!(filter.matches(&entry(100, 1)));
+        assert!(!filter.matches(&entry(200, 1)));
+    }
+
+    #[test]
+    fn filter_by_kind() {
+        let filter = HistoryFilter::default()
+            .kind(EventKind::Alarm)
+            .kind(EventKind::Tamper);
+        assert!(filter.matches(&entry(0, 1)));
+        assert!(filter.matches(&entry(0, 5)));
+        assert!(!filter.matches(&entry(0, 2)));
+        assert!(HistoryFilter::default().matches(&entry(0, 2)));
+    }
+
+    #[test]
+    fn filter_by_unknown_kind() {
+        let filter = HistoryFilter::default().kind(EventKind::Unknown(42));
+        assert_eq!(entry(0, 42).kind, EventKind::Unknown(42));
+        assert!(filter.matches(&entry(0, 42)));
+        assert!(!filter.matches(&entry(0, 43)));
+    }
+
+    #[test]
+    fn history_list() {
+        let json = serde_json::json!({ "hisrows": [{ "d": 1, "a": 1, "e": 2 }] });
+        let history: History = serde_json::from_value(json).unwrap();
+        assert_eq!(history.into_result().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn history_skips_malformed_rows() {
+        let json = serde_json::json!({ "hisrows": [
+            { "d": 1, "a": 1, "e": 2 },
+            { "d": "yesterday", "a": 1, "e": 2 },
+            { "a": 1 },
+            { "d": 3, "a": 2, "e": 1 }
+        ] });
+        let history: History = serde_json::from_value(json).unwrap();
+        let timestamps: Vec<_> = history
+            .into_result()
+            .unwrap()
+            .iter()
+            .map(|entry| entry.timestamp)
+            .collect();
+        assert_eq!(timestamps, [1, 3]);
+    }
+}
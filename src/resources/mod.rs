@@ -1,8 +1,19 @@
 pub mod devices;
+pub mod dimmers;
+pub mod history;
 pub mod panel;
+pub mod readings;
 pub mod response;
+pub mod shutters;
+pub mod switches;
+pub mod thermostats;
 
-use crate::errors::Result;
+use serde::Deserialize;
+
+use std::marker::PhantomData;
+
+use crate::constants::DeviceKind;
+use crate::errors::{Error, Result};
 
 /// A trait implemented by raw API response types to convert themselves into
 /// the domain type the caller actually needs.
@@ -17,3 +28,77 @@ pub trait ApiResponse {
     /// Validate the response and extract the inner value.
     fn into_result(self) -> Result<Self::Type>;
 }
+
+/// Deserialize each row on its own, skipping the rows that fail so that one
+/// malformed row does not hide all others.
+pub(crate) fn parse_rows<T>(rows: Vec<serde_json::Value>) -> Vec<T>
+where
+    T: serde::de::DeserializeOwned,
+{
+    partition_rows(rows).0
+}
+
+/// Deserialize each row on its own, returning the rows that fail next to
+/// the parsed ones.
+pub(crate) fn partition_rows<T>(rows: Vec<serde_json::Value>) -> (Vec<T>, Vec<serde_json::Value>)
+where
+    T: serde::de::DeserializeOwned,
+{
+    let mut parsed = Vec::with_capacity(rows.len());
+    let mut malformed = Vec::new();
+
+    for row in rows {
+        match T::deserialize(&row) {
+            Ok(item) => parsed.push(item),
+            Err(_error) => {
+                trace_event!(warn, error = %_error, "skipping a malformed row");
+                malformed.push(row);
+            }
+        }
+    }
+
+    (parsed, malformed)
+}
+
+/// A device type that is listed in the `deviceListGet` response next to the
+/// devices of other kinds.
+pub(crate) trait OfKind: serde::de::DeserializeOwned {
+    /// Whether rows of the given device kind describe this type.
+    fn of_kind(kind: DeviceKind) -> bool;
+}
+
+/// The devices of type `T` contained in the `deviceListGet` response. Rows of
+/// other device kinds (including unknown ones) and malformed rows are skipped.
+#[derive(Deserialize)]
+#[serde(bound = "")]
+pub(crate) struct KindList<T> {
+    #[serde(rename = "senrows")]
+    list: Vec<serde_json::Value>,
+    #[serde(skip)]
+    kind: PhantomData<T>,
+}
+
+impl<T: OfKind> ApiResponse for KindList<T> {
+    type Type = Vec<T>;
+
+    fn into_result(self) -> Result<Self::Type> {
+        let matches = |row: &serde_json::Value| {
+            row.get("type")
+                .and_then(|kind| DeviceKind::deserialize(kind).ok())
+                .is_some_and(T::of_kind)
+        };
+
+        Ok(parse_rows(self.list.into_iter().filter(matches).collect()))
+    }
+}
+
+/// Check that a position or level is a percentage.
+pub(crate) fn validate_percent(percent: u8) -> Result<u8> {
+    if percent > 100 {
+        return Err(Error::InvalidArgument(format!(
+            "expected a percentage between 0 and 100, got {percent}"
+        )));
+    }
+
+    Ok(percent)
+}
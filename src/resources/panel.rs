@@ -1,44 +1,114 @@
 use serde::{Deserialize, Serialize};
 
-use crate::constants::Mode;
+use std::collections::BTreeMap;
+
+use crate::constants::{Area, Mode};
 use crate::errors::Result;
 use crate::resources::ApiResponse;
+use crate::utils::{deserialize_flag, deserialize_optional_number};
 
-/// Represents the status of Area 1 and 2.
-#[derive(Serialize, Deserialize, Debug)]
+/// Represents the condition of every area of the panel.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Modes {
-    /// Mode of Area 1
-    pub area1: Mode,
+    /// The condition of each area, ordered by area number
+    pub areas: Vec<AreaCondition>,
+
+    /// Panel-wide fault and alert messages (e.g. tamper, power failure or
+    /// interference), keyed by the field name the panel uses
+    pub alerts: BTreeMap<String, String>,
+}
+
+impl Modes {
+    /// The condition of the given area, if the panel reported it.
+    #[must_use]
+    pub fn get(&self, area: Area) -> Option<&AreaCondition> {
+        self.areas.iter().find(|condition| condition.area == area)
+    }
 
-    /// Mode of Area 2
-    pub area2: Mode,
+    /// The mode of the given area, if the panel reported it.
+    #[must_use]
+    pub fn mode(&self, area: Area) -> Option<Mode> {
+        self.get(area).map(|condition| condition.mode)
+    }
+}
+
+/// The condition of a single area
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AreaCondition {
+    /// The area
+    pub area: Area,
+
+    /// The current mode
+    pub mode: Mode,
+
+    /// Whether an alarm is in progress
+    pub alarm: bool,
+
+    /// Seconds left before an alarm is raised after an entry was detected
+    pub entry_delay: Option<u32>,
+
+    /// Seconds left until the area is armed
+    pub exit_delay: Option<u32>,
 }
 
 #[derive(Deserialize)]
 pub(crate) struct Condition {
-    forms: Forms,
+    forms: BTreeMap<String, serde_json::Value>,
+    #[serde(default)]
+    updates: BTreeMap<String, serde_json::Value>,
 }
 
 impl ApiResponse for Condition {
     type Type = Modes;
 
+    /// Forms other than `pcondformN` and forms that cannot be parsed are
+    /// skipped.
     fn into_result(self) -> Result<Self::Type> {
-        Ok(Modes {
-            area1: self.forms.pcondform1.mode,
-            area2: self.forms.pcondform2.mode,
-        })
-    }
-}
+        let mut areas: Vec<_> = self
+            .forms
+            .into_iter()
+            .filter_map(|(name, form)| {
+                let area: u8 = name.strip_prefix("pcondform")?.parse().ok()?;
+                let form: PCondForm = serde_json::from_value(form)
+                    .inspect_err(|_error| {
+                        trace_event!(warn, form = %name, error = %_error, "skipping a malformed form");
+                    })
+                    .ok()?;
+                Some(AreaCondition {
+                    area: Area::from(area),
+                    mode: form.mode,
+                    alarm: form.alarm,
+                    entry_delay: form.entry_delay.filter(|&secs| secs > 0),
+                    exit_delay: form.exit_delay.filter(|&secs| secs > 0),
+                })
+            })
+            .collect();
+        areas.sort_by_key(|condition| u8::from(condition.area));
 
-#[derive(Deserialize)]
-struct Forms {
-    pcondform1: PCondForm,
-    pcondform2: PCondForm,
+        let alerts = self
+            .updates
+            .into_iter()
+            .filter_map(|(name, value)| match value {
+                serde_json::Value::String(text) if !text.trim().is_empty() => {
+                    Some((name, text.trim().to_owned()))
+                }
+                _ => None,
+            })
+            .collect();
+
+        Ok(Modes { areas, alerts })
+    }
 }
 
 #[derive(Deserialize)]
 struct PCondForm {
     mode: Mode,
+    #[serde(default, deserialize_with = "deserialize_flag")]
+    alarm: bool,
+    #[serde(default, deserialize_with = "deserialize_optional_number")]
+    entry_delay: Option<u32>,
+    #[serde(default, deserialize_with = "deserialize_optional_number")]
+    exit_delay: Option<u32>,
 }
 
 #[cfg(test)]
@@ -55,7 +125,56 @@ mod tests {
         });
         let condition: Condition = serde_json::from_value(json).unwrap();
         let modes = condition.into_result().unwrap();
-        assert_eq!(modes.area1, Mode::Disarmed);
-        assert_eq!(modes.area2, Mode::Armed);
+        assert_eq!(modes.mode(Area::Area1), Some(Mode::Disarmed));
+        assert_eq!(modes.mode(Area::Area2), Some(Mode::Armed));
+        assert!(modes.alerts.is_empty());
+    }
+
+    #[test]
+    fn deserialize_more_areas_and_details() {
+        let json = serde_json::json!({
+            "forms": {
+                "pcondform3": { "mode": "1", "alarm": "0", "entry_delay": "", "exit_delay": "25" },
+                "pcondform1": { "mode": "0", "alarm": "1", "entry_delay": "0", "exit_delay": "0" },
+                "pcondform2": { "mode": "2", "entry_delay": 12 },
+                "otherform": { "mode": 0 },
+                "statusform": { "battery": "1", "rssi": "5" }
+            },
+            "updates": {
+                "alarm_status": "",
+                "tamper_status": "{WEB_MSG_TAMPER} Hallway",
+                "rssi": 5
+            }
+        });
+        let condition: Condition = serde_json::from_value(json).unwrap();
+        let modes = condition.into_result().unwrap();
+
+        let areas: Vec<_> = modes.areas.iter().map(|c| c.area).collect();
+        assert_eq!(areas, [Area::Area1, Area::Area2, Area::Other(3)]);
+
+        assert!(modes.areas[0].alarm);
+        assert_eq!(modes.areas[0].exit_delay, None);
+        assert_eq!(modes.areas[1].mode, Mode::Home1);
+        assert_eq!(modes.areas[1].entry_delay, Some(12));
+        assert_eq!(modes.areas[2].exit_delay, Some(25));
+
+        assert_eq!(modes.alerts.len(), 1);
+        assert_eq!(modes.alerts["tamper_status"], "{WEB_MSG_TAMPER} Hallway");
+    }
+
+    #[test]
+    fn unknown_and_malformed_forms() {
+        let json = serde_json::json!({
+            "forms": {
+                "pcondform1": { "mode": 7 },
+                "pcondform2": { "alarm": "0" },
+                "pcondform3": { "mode": "4" }
+            }
+        });
+        let condition: Condition = serde_json::from_value(json).unwrap();
+        let modes = condition.into_result().unwrap();
+        assert_eq!(modes.mode(Area::Area1), Some(Mode::Unknown(7)));
+        assert_eq!(modes.mode(Area::Area2), None);
+        assert_eq!(modes.mode(Area::Other(3)), Some(Mode::Home3));
     }
 }
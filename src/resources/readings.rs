@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumIter, IntoEnumIterator};
+
+use crate::utils::deserialize_optional_float;
+
+/// A physical quantity reported by a sensor or meter
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Display, EnumIter)]
+pub enum Quantity {
+    /// Temperature in °C
+    Temperature,
+
+    /// Relative humidity in %
+    Humidity,
+
+    /// Illuminance in lx
+    Illuminance,
+
+    /// Radon concentration in Bq/m³
+    Radon,
+
+    /// Electrical power in W
+    Power,
+
+    /// Consumed energy (electricity or heat) in kWh
+    Energy,
+
+    /// Consumed volume (water or gas) in m³
+    Volume,
+}
+
+impl Quantity {
+    /// The unit the value of this quantity is given in.
+    #[must_use]
+    pub fn unit(self) -> &'static str {
+        match self {
+            Quantity::Temperature => "°C",
+            Quantity::Humidity => "%",
+            Quantity::Illuminance => "lx",
+            Quantity::Radon => "Bq/m³",
+            Quantity::Power => "W",
+            Quantity::Energy => "kWh",
+            Quantity::Volume => "m³",
+        }
+    }
+}
+
+/// Measurements reported by sensors and meters
+///
+/// Only the fields a device actually reports are set, e.g. a room sensor has
+/// a temperature and humidity while a water meter has a volume.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct Readings {
+    /// Temperature in °C
+    #[serde(
+        default,
+        deserialize_with = "deserialize_optional_float",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub temperature: Option<f64>,
+    /// Relative humidity in %
+    #[serde(
+        default,
+        deserialize_with = "deserialize_optional_float",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub humidity: Option<f64>,
+    /// Illuminance in lx
+    #[serde(
+        rename(deserialize = "lux"),
+        default,
+        deserialize_with = "deserialize_optional_float",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub illuminance: Option<f64>,
+    /// Radon concentration in Bq/m³
+    #[serde(
+        default,
+        deserialize_with = "deserialize_optional_float",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub radon: Option<f64>,
+    /// Electrical power in W
+    #[serde(
+        default,
+        deserialize_with = "deserialize_optional_float",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub power: Option<f64>,
+    /// Consumed energy (electricity or heat) in kWh
+    #[serde(
+        default,
+        deserialize_with = "deserialize_optional_float",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub energy: Option<f64>,
+    /// Consumed volume (water or gas) in m³
+    #[serde(
+        default,
+        deserialize_with = "deserialize_optional_float",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub volume: Option<f64>,
+}
+
+impl Readings {
+    /// The value of the given quantity, if reported.
+    #[must_use]
+    pub fn get(&self, quantity: Quantity) -> Option<f64> {
+        match quantity {
+            Quantity::Temperature => self.temperature,
+            Quantity::Humidity => self.humidity,
+            Quantity::Illuminance => self.illuminance,
+            Quantity::Radon => self.radon,
+            Quantity::Power => self.power,
+            Quantity::Energy => self.energy,
+            Quantity::Volume => self.volume,
+        }
+    }
+
+    /// Iterate over all reported quantities and their values.
+    pub fn iter(&self) -> impl Iterator<Item = (Quantity, f64)> + '_ {
+        Quantity::iter().filter_map(|quantity| Some((quantity, self.get(quantity)?)))
+    }
+
+    /// Whether no quantity is reported.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserialize_room_sensor() {
+        let json = serde_json::json!({ "temperature": "21.5", "humidity": 48, "lux": "" });
+        let readings: Readings = serde_json::from_value(json).unwrap();
+        assert_eq!(readings.temperature, Some(21.5));
+        assert_eq!(readings.humidity, Some(48.0));
+        assert_eq!(readings.illuminance, None);
+        assert_eq!(
+            readings.iter().collect::<Vec<_>>(),
+            [(Quantity::Temperature, 21.5), (Quantity::Humidity, 48.0)]
+        );
+    }
+
+    #[test]
+    fn empty_readings() {
+        let readings: Readings = serde_json::from_value(serde_json::json!({})).unwrap();
+        assert!(readings.is_empty());
+        assert_eq!(
+            serde_json::to_value(&readings).unwrap(),
+            serde_json::json!({})
+        );
+    }
+
+    #[test]
+    fn units() {
+        assert_eq!(Quantity::Temperature.unit(), "°C");
+        assert_eq!(Quantity::Volume.unit(), "m³");
+    }
+}
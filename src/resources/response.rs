@@ -14,7 +14,7 @@ impl ApiResponse for Response {
     type Type = String;
 
     fn into_result(self) -> Result<Self::Type> {
-        if self.result == Status::Error {
+        if self.result != Status::Ok {
             return Err(Error::Panel(self.message));
         }
 
@@ -33,6 +33,13 @@ mod tests {
         assert_eq!(resp.into_result().unwrap(), "token123");
     }
 
+    #[test]
+    fn unknown_result_is_an_error() {
+        let json = serde_json::json!({ "result": 2, "message": "busy" });
+        let resp: Response = serde_json::from_value(json).unwrap();
+        assert!(matches!(resp.into_result().unwrap_err(), Error::Panel(m) if m == "busy"));
+    }
+
     #[test]
     fn error_response() {
         let json = serde_json::json!({ "result": 0, "message": "something failed" });
@@ -0,0 +1,160 @@
+use serde::{Deserialize, Serialize};
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::constants::DeviceKind;
+use crate::errors::{Error, Result};
+use crate::resources::{OfKind, validate_percent};
+use crate::utils::deserialize_optional_number;
+
+/// The state of a shutter or awning
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Shutter {
+    /// The sensor ID
+    pub sid: String,
+    /// The device kind
+    #[serde(rename(deserialize = "type"))]
+    pub kind: DeviceKind,
+    /// The device name
+    pub name: String,
+    /// How far the shutter is open in percent (0 = closed, 100 = open)
+    #[serde(
+        rename(deserialize = "level"),
+        default,
+        deserialize_with = "deserialize_optional_number"
+    )]
+    pub position: Option<u8>,
+}
+
+/// A command to move a shutter or awning
+///
+/// Parses from `open`, `close`, `stop` or a position in percent.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ShutterCommand {
+    /// Open completely
+    Open,
+
+    /// Close completely
+    Close,
+
+    /// Stop moving
+    Stop,
+
+    /// Move to the given position in percent (0 = closed, 100 = open)
+    Position(u8),
+}
+
+impl ShutterCommand {
+    /// The form fields sent to the panel.
+    pub(crate) fn to_form(self) -> Result<(&'static str, String)> {
+        Ok(match self {
+            ShutterCommand::Open => ("action", "open".into()),
+            ShutterCommand::Close => ("action", "close".into()),
+            ShutterCommand::Stop => ("action", "stop".into()),
+            ShutterCommand::Position(position) => {
+                ("level", validate_percent(position)?.to_string())
+            }
+        })
+    }
+}
+
+impl fmt::Display for ShutterCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShutterCommand::Open => f.write_str("open"),
+            ShutterCommand::Close => f.write_str("close"),
+            ShutterCommand::Stop => f.write_str("stop"),
+            ShutterCommand::Position(percent) => write!(f, "{percent}%"),
+        }
+    }
+}
+
+impl FromStr for ShutterCommand {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<ShutterCommand> {
+        match s.to_ascii_lowercase().as_str() {
+            "open" => Ok(ShutterCommand::Open),
+            "close" => Ok(ShutterCommand::Close),
+            "stop" => Ok(ShutterCommand::Stop),
+            other => other
+                .trim_end_matches('%')
+                .parse()
+                .map_err(|_| Error::InvalidArgument(format!("invalid shutter command: {s}")))
+                .and_then(validate_percent)
+                .map(ShutterCommand::Position),
+        }
+    }
+}
+
+impl OfKind for Shutter {
+    fn of_kind(kind: DeviceKind) -> bool {
+        kind.is_shutter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::{ApiResponse, KindList};
+
+    #[test]
+    fn only_shutters_are_kept() {
+        let json = serde_json::json!({
+            "senrows": [
+                { "sid": "RF:01", "type": 4, "name": "Door", "status_ex": 0 },
+                { "sid": "ZS:02", "type": 76, "name": "Kitchen", "level": "40" },
+                { "sid": "ZS:03", "type": 80, "name": "Terrace", "level": "" }
+            ]
+        });
+        let list: KindList<Shutter> = serde_json::from_value(json).unwrap();
+        let shutters = list.into_result().unwrap();
+        assert_eq!(shutters.len(), 2);
+        assert_eq!(shutters[0].position, Some(40));
+        assert_eq!(shutters[1].kind, DeviceKind::Awning);
+        assert_eq!(shutters[1].position, None);
+    }
+
+    #[test]
+    fn parse_command() {
+        assert_eq!(
+            "Open".parse::<ShutterCommand>().unwrap(),
+            ShutterCommand::Open
+        );
+        assert_eq!(
+            "stop".parse::<ShutterCommand>().unwrap(),
+            ShutterCommand::Stop
+        );
+        assert_eq!(
+            "75%".parse::<ShutterCommand>().unwrap(),
+            ShutterCommand::Position(75)
+        );
+        assert!("101".parse::<ShutterCommand>().is_err());
+        assert!("up".parse::<ShutterCommand>().is_err());
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for command in [
+            ShutterCommand::Open,
+            ShutterCommand::Close,
+            ShutterCommand::Stop,
+            ShutterCommand::Position(40),
+        ] {
+            assert_eq!(
+                command.to_string().parse::<ShutterCommand>().unwrap(),
+                command
+            );
+        }
+    }
+
+    #[test]
+    fn position_out_of_range_is_rejected() {
+        assert!(ShutterCommand::Position(101).to_form().is_err());
+        assert_eq!(
+            ShutterCommand::Position(30).to_form().unwrap(),
+            ("level", "30".into())
+        );
+    }
+}
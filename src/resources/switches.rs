@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+
+use crate::constants::DeviceKind;
+use crate::resources::OfKind;
+use crate::utils::{deserialize_flag, deserialize_optional_float};
+
+/// The state of a power switch or smart plug
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Switch {
+    /// The sensor ID
+    pub sid: String,
+    /// The device kind
+    #[serde(rename(deserialize = "type"))]
+    pub kind: DeviceKind,
+    /// The device name
+    pub name: String,
+    /// Whether the switch is on
+    #[serde(
+        rename(deserialize = "status_ex"),
+        deserialize_with = "deserialize_flag"
+    )]
+    pub on: bool,
+    /// The current power draw in watts, if the switch has a meter
+    #[serde(default, deserialize_with = "deserialize_optional_float")]
+    pub power: Option<f64>,
+    /// The consumed energy in kilowatt hours, if the switch has a meter
+    #[serde(default, deserialize_with = "deserialize_optional_float")]
+    pub energy: Option<f64>,
+}
+
+impl OfKind for Switch {
+    fn of_kind(kind: DeviceKind) -> bool {
+        kind.is_switch()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::{ApiResponse, KindList};
+
+    #[test]
+    fn only_valid_switches_are_kept() {
+        let json = serde_json::json!({
+            "senrows": [
+                { "sid": "RF:01", "type": 4, "name": "Door", "status_ex": 0 },
+                { "sid": "RF:02", "type": 24, "name": "Lamp", "status_ex": "1" },
+                { "sid": "RF:04", "type": 24, "name": "Broken" },
+                {
+                    "sid": "RF:03",
+                    "type": 48,
+                    "name": "Washer",
+                    "status_ex": 0,
+                    "power": "12.5",
+                    "energy": 3.25
+                }
+            ]
+        });
+        let list: KindList<Switch> = serde_json::from_value(json).unwrap();
+        let switches = list.into_result().unwrap();
+        assert_eq!(switches.len(), 2);
+
+        assert_eq!(switches[0].sid, "RF:02");
+        assert!(switches[0].on);
+        assert_eq!(switches[0].power, None);
+
+        assert_eq!(switches[1].kind, DeviceKind::PowerSwitchMeters);
+        assert!(!switches[1].on);
+        assert_eq!(switches[1].power, Some(12.5));
+        assert_eq!(switches[1].energy, Some(3.25));
+    }
+}
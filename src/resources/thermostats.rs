@@ -0,0 +1,152 @@
+use serde::{Deserialize, Serialize};
+
+use crate::constants::{DeviceKind, ThermostatMode};
+use crate::errors::{Error, Result};
+use crate::resources::OfKind;
+use crate::utils::{deserialize_optional_float, deserialize_optional_number};
+
+/// The state of a thermostat or radiator valve
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Thermostat {
+    /// The sensor ID
+    pub sid: String,
+    /// The device kind
+    #[serde(rename(deserialize = "type"))]
+    pub kind: DeviceKind,
+    /// The device name
+    pub name: String,
+    /// The measured room temperature in °C
+    #[serde(
+        rename(deserialize = "act_temp"),
+        default,
+        deserialize_with = "deserialize_optional_float"
+    )]
+    pub actual_temperature: Option<f64>,
+    /// The target temperature in °C
+    #[serde(
+        rename(deserialize = "set_temp"),
+        default,
+        deserialize_with = "deserialize_optional_float"
+    )]
+    pub target_temperature: Option<f64>,
+    /// The valve opening in percent, for radiator thermostats
+    #[serde(default, deserialize_with = "deserialize_optional_number")]
+    pub valve: Option<u8>,
+    /// The operating mode, if reported by the device
+    #[serde(
+        rename(deserialize = "thermo_mode"),
+        default,
+        deserialize_with = "deserialize_optional_mode"
+    )]
+    pub mode: Option<ThermostatMode>,
+}
+
+/// Deserialize an optional thermostat mode from a number or a numeric string.
+fn deserialize_optional_mode<'de, D>(
+    deserializer: D,
+) -> std::result::Result<Option<ThermostatMode>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(deserialize_optional_number::<u8, D>(deserializer)?.map(ThermostatMode::from))
+}
+
+impl Thermostat {
+    /// The lowest target temperature accepted by the panel, in °C
+    pub const MIN_TARGET_TEMPERATURE: f64 = 5.0;
+
+    /// The highest target temperature accepted by the panel, in °C
+    pub const MAX_TARGET_TEMPERATURE: f64 = 30.0;
+}
+
+/// Check that a target temperature is within the range accepted by the panel
+/// and a multiple of 0.5 °C.
+pub(crate) fn validate_target_temperature(celsius: f64) -> Result<f64> {
+    let (min, max) = (
+        Thermostat::MIN_TARGET_TEMPERATURE,
+        Thermostat::MAX_TARGET_TEMPERATURE,
+    );
+
+    if !(min..=max).contains(&celsius) || (celsius * 2.0).fract() != 0.0 {
+        return Err(Error::InvalidArgument(format!(
+            "target temperature must be between {min} and {max} °C in steps of 0.5 °C, got {celsius}"
+        )));
+    }
+
+    Ok(celsius)
+}
+
+impl OfKind for Thermostat {
+    fn of_kind(kind: DeviceKind) -> bool {
+        kind.is_thermostat()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::{ApiResponse, KindList};
+
+    #[test]
+    fn only_thermostats_are_kept() {
+        let json = serde_json::json!({
+            "senrows": [
+                { "sid": "RF:01", "type": 4, "name": "Door", "status_ex": 0 },
+                {
+                    "sid": "ZS:02",
+                    "type": 79,
+                    "name": "Living Room",
+                    "act_temp": "20.5",
+                    "set_temp": 21,
+                    "valve": "35",
+                    "thermo_mode": 1
+                },
+                {
+                    "sid": "ZS:03",
+                    "type": 73,
+                    "name": "Hallway",
+                    "act_temp": "",
+                    "set_temp": "",
+                    "thermo_mode": "0"
+                }
+            ]
+        });
+        let list: KindList<Thermostat> = serde_json::from_value(json).unwrap();
+        let thermostats = list.into_result().unwrap();
+        assert_eq!(thermostats.len(), 2);
+
+        assert_eq!(thermostats[0].actual_temperature, Some(20.5));
+        assert_eq!(thermostats[0].target_temperature, Some(21.0));
+        assert_eq!(thermostats[0].valve, Some(35));
+        assert_eq!(thermostats[0].mode, Some(ThermostatMode::Auto));
+
+        assert_eq!(thermostats[1].actual_temperature, None);
+        assert_eq!(thermostats[1].valve, None);
+        assert_eq!(thermostats[1].mode, Some(ThermostatMode::Off));
+    }
+
+    #[test]
+    fn thermostat_without_mode() {
+        let json = serde_json::json!({
+            "sid": "ZS:04",
+            "type": 79,
+            "name": "Bathroom",
+            "act_temp": "22",
+            "set_temp": "22"
+        });
+        let thermostat: Thermostat = serde_json::from_value(json).unwrap();
+        assert_eq!(thermostat.mode, None);
+        assert_eq!(thermostat.actual_temperature, Some(22.0));
+    }
+
+    #[test]
+    fn target_temperature_range() {
+        assert!(validate_target_temperature(5.0).is_ok());
+        assert!(validate_target_temperature(21.5).is_ok());
+        assert!(validate_target_temperature(30.0).is_ok());
+        assert!(validate_target_temperature(4.5).is_err());
+        assert!(validate_target_temperature(30.5).is_err());
+        assert!(validate_target_temperature(21.3).is_err());
+        assert!(validate_target_temperature(f64::NAN).is_err());
+    }
+}
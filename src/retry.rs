@@ -0,0 +1,196 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::errors::Error;
+
+type Predicate = Arc<dyn Fn(&Error) -> bool + Send + Sync>;
+
+/// Controls how often and how fast failed requests are retried.
+///
+/// Retries happen in addition to the single retry with a fresh session when
+/// the panel reports a session timeout. Requests that change state on the
+/// panel and are not idempotent (such as [`Client::change_mode`]) are only
+/// retried if the request provably never reached the panel, i.e. the
+/// connection could not be established.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use alarmate::RetryPolicy;
+///
+/// let policy = RetryPolicy::default()
+///     .max_attempts(5)
+///     .initial_backoff(Duration::from_millis(500))
+///     .retry_if(|err| err.is_transient() || matches!(err, alarmate::Error::Panel(_)));
+/// ```
+///
+/// [`Client::change_mode`]: crate::Client::change_mode
+#[derive(Clone)]
+#[must_use]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    multiplier: u32,
+    jitter: bool,
+    retry_if: Predicate,
+}
+
+impl RetryPolicy {
+    /// A policy that never retries.
+    pub fn none() -> RetryPolicy {
+        RetryPolicy::default().max_attempts(1)
+    }
+
+    /// Set the maximum number of attempts, including the first one.
+    pub fn max_attempts(mut self, max_attempts: u32) -> RetryPolicy {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Set the delay before the first retry.
+    pub fn initial_backoff(mut self, backoff: Duration) -> RetryPolicy {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    /// Set the upper bound for the delay between attempts.
+    pub fn max_backoff(mut self, backoff: Duration) -> RetryPolicy {
+        self.max_backoff = backoff;
+        self
+    }
+
+    /// Set the factor the delay grows by after each attempt.
+    pub fn multiplier(mut self, multiplier: u32) -> RetryPolicy {
+        self.multiplier = multiplier.max(1);
+        self
+    }
+
+    /// Whether to randomize each delay between half and all of its value.
+    pub fn jitter(mut self, jitter: bool) -> RetryPolicy {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Decide which errors are retried. Defaults to [`Error::is_transient`].
+    pub fn retry_if<F>(mut self, predicate: F) -> RetryPolicy
+    where
+        F: Fn(&Error) -> bool + Send + Sync + 'static,
+    {
+        self.retry_if = Arc::new(predicate);
+        self
+    }
+
+    /// Whether another attempt should be made after `attempt` failed with
+    /// `error`.
+    pub(crate) fn should_retry(&self, attempt: u32, error: &Error, idempotent: bool) -> bool {
+        attempt < self.max_attempts && (idempotent || error.is_connect()) && (self.retry_if)(error)
+    }
+
+    /// The delay before the attempt following `attempt`.
+    pub(crate) fn backoff(&self, attempt: u32) -> Duration {
+        let factor = self.multiplier.saturating_pow(attempt.saturating_sub(1));
+        let delay = self
+            .initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff);
+
+        if self.jitter {
+            delay.mul_f64(0.5 + fastrand::f64() / 2.0)
+        } else {
+            delay
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    /// Three attempts with exponential backoff starting at 250 ms, capped at
+    /// 5 s, with jitter, retrying [transient](Error::is_transient) errors.
+    fn default() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(5),
+            multiplier: 2,
+            jitter: true,
+            retry_if: Arc::new(Error::is_transient),
+        }
+    }
+}
+
+impl fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_attempts", &self.max_attempts)
+            .field("initial_backoff", &self.initial_backoff)
+            .field("max_backoff", &self.max_backoff)
+            .field("multiplier", &self.multiplier)
+            .field("jitter", &self.jitter)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server_error() -> Error {
+        Error::UnexpectedResponse {
+            status: reqwest::StatusCode::SERVICE_UNAVAILABLE,
+            body: String::new(),
+        }
+    }
+
+    #[test]
+    fn exponential_backoff_is_capped() {
+        let policy = RetryPolicy::default()
+            .jitter(false)
+            .initial_backoff(Duration::from_millis(100))
+            .max_backoff(Duration::from_millis(300));
+
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(300));
+        assert_eq!(policy.backoff(30), Duration::from_millis(300));
+    }
+
+    #[test]
+    fn jitter_stays_within_bounds() {
+        let policy = RetryPolicy::default().initial_backoff(Duration::from_millis(100));
+        for _ in 0..100 {
+            let delay = policy.backoff(1);
+            assert!(delay >= Duration::from_millis(50) && delay <= Duration::from_millis(100));
+        }
+    }
+
+    #[test]
+    fn stops_after_max_attempts() {
+        let policy = RetryPolicy::default().max_attempts(2);
+        assert!(policy.should_retry(1, &server_error(), true));
+        assert!(!policy.should_retry(2, &server_error(), true));
+        assert!(!RetryPolicy::none().should_retry(1, &server_error(), true));
+    }
+
+    #[test]
+    fn non_idempotent_requests_only_retry_unsent() {
+        let policy = RetryPolicy::default();
+        assert!(!policy.should_retry(1, &server_error(), false));
+    }
+
+    #[test]
+    fn custom_predicate() {
+        let policy = RetryPolicy::default().retry_if(|e| matches!(e, Error::Panel(_)));
+        assert!(policy.should_retry(1, &Error::Panel("busy".into()), true));
+        assert!(!policy.should_retry(1, &server_error(), true));
+    }
+
+    #[tokio::test]
+    async fn connection_errors_are_retried_for_non_idempotent_requests() {
+        // Nothing listens on port 1, so the request never leaves the client.
+        let error: Error = reqwest::get("http://127.0.0.1:1/")
+            .await
+            .unwrap_err()
+            .into();
+        assert!(RetryPolicy::default().should_retry(1, &error, false));
+    }
+}
@@ -0,0 +1,475 @@
+//! An in-process fake Lupusec panel for integration tests
+//!
+//! [`Simulator`] serves the panel's HTTP API on a local port and keeps its
+//! state between requests, so code using [`Client`] can be tested without
+//! hardware:
+//!
+//! ```rust
+//! use alarmate::simulator::Simulator;
+//! use alarmate::{Area, DeviceKind, Mode};
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> alarmate::Result {
+//! let panel = Simulator::start("admin", "changeme").await;
+//! panel.add_device("RF:01", DeviceKind::DoorContact, "Front Door", Area::Area1);
+//!
+//! let client = panel.client().build()?;
+//! client.change_mode(Area::Area1, Mode::Armed).await?;
+//! assert_eq!(panel.mode(Area::Area1), Some(Mode::Armed));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Like a real panel it
+//!
+//! - answers requests without valid basic auth credentials with `401`,
+//! - ends the session after [`set_session_timeout`](Simulator::set_session_timeout)
+//!   of inactivity or on [`expire_session`](Simulator::expire_session), and
+//!   answers the next request with the HTML page redirecting to
+//!   `/action/login`,
+//! - requires a token from `tokenGet` in the `X-Token` header of `POST`
+//!   requests, which expires after
+//!   [`set_token_lifetime`](Simulator::set_token_lifetime), and
+//! - accepts a request to arm an area with an open door contact, but keeps
+//!   the area's mode.
+//!
+//! It serves `tokenGet`, `panelCondGet`, `panelCondPost`, `deviceListGet`,
+//! `deviceSwitchPSSPost`, `deviceThermostatPost`, `deviceShutterPost`,
+//! `deviceDimmerPost` and `historyGet`.
+
+use serde_json::{Value, json};
+use wiremock::matchers::{basic_auth, path_regex};
+use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::builder::ClientBuilder;
+use crate::client::Client;
+use crate::constants::{Area, DeviceKind, Mode};
+
+/// The page the panel serves instead of JSON once the session has expired
+const LOGIN_PAGE: &str = "<html><head><meta http-equiv=\"refresh\" \
+    content=\"0; url=/action/login\"></head><body></body></html>";
+
+/// A fake Lupusec panel listening on a local port
+///
+/// The panel is shut down when the simulator is dropped.
+pub struct Simulator {
+    server: MockServer,
+    username: String,
+    password: String,
+    panel: Arc<Mutex<Panel>>,
+}
+
+impl Simulator {
+    /// Start a panel accepting the given credentials, with two disarmed
+    /// areas and no devices.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no local port can be bound.
+    pub async fn start(username: &str, password: &str) -> Simulator {
+        let server = MockServer::start().await;
+        let panel = Arc::new(Mutex::new(Panel::new()));
+
+        Mock::given(path_regex("^/action/"))
+            .and(basic_auth(username, password))
+            .respond_with(Handler(Arc::clone(&panel)))
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        Mock::given(path_regex(""))
+            .respond_with(ResponseTemplate::new(401))
+            .with_priority(10)
+            .mount(&server)
+            .await;
+
+        Simulator {
+            server,
+            username: username.into(),
+            password: password.into(),
+            panel,
+        }
+    }
+
+    /// The base URL of the panel's API, e.g. `http://127.0.0.1:41234/action/`.
+    pub fn base_url(&self) -> String {
+        format!("{}/action/", self.server.uri())
+    }
+
+    /// A [`ClientBuilder`] for this panel with the credentials it accepts.
+    pub fn client(&self) -> ClientBuilder {
+        Client::builder(&self.username, self.password.as_str()).base_url(self.base_url())
+    }
+
+    /// The current mode of an area, `None` if the panel does not have it.
+    pub fn mode(&self, area: Area) -> Option<Mode> {
+        self.panel().areas.get(&u8::from(area)).copied()
+    }
+
+    /// Set the mode of an area, adding the area if the panel does not have
+    /// it yet.
+    pub fn set_mode(&self, area: Area, mode: Mode) {
+        self.panel().areas.insert(u8::from(area), mode);
+    }
+
+    /// Show an alert message in `panelCondGet` (e.g. `"rf_ex"` → `"Door
+    /// open"`), or clear it with an empty text.
+    pub fn set_alert(&self, name: &str, text: &str) {
+        let mut panel = self.panel();
+        if text.is_empty() {
+            panel.alerts.remove(name);
+        } else {
+            panel.alerts.insert(name.into(), text.into());
+        }
+    }
+
+    /// Add a closed device without faults.
+    pub fn add_device(&self, sid: &str, kind: DeviceKind, name: &str, area: Area) {
+        self.panel().devices.push(json!({
+            "sid": sid,
+            "type": u8::from(kind),
+            "name": name,
+            "area": u8::from(area),
+            "zone": 0,
+            "status_ex": 0,
+            "cond_ok": 1,
+            "battery_ok": 1,
+            "tamper_ok": 1,
+        }));
+    }
+
+    /// Overwrite fields of a device as the panel names them in
+    /// `deviceListGet`, e.g. `json!({"status_ex": 1, "battery_ok": 0})` for
+    /// an open door with a low battery.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no device with the given sensor ID or `fields` is
+    /// not a JSON object.
+    pub fn update_device(&self, sid: &str, fields: Value) {
+        let mut panel = self.panel();
+        let device = panel
+            .device_mut(sid)
+            .unwrap_or_else(|| panic!("no simulated device with sensor ID {sid}"));
+        let Value::Object(fields) = fields else {
+            panic!("device fields must be a JSON object");
+        };
+        device.extend(fields);
+    }
+
+    /// The `deviceListGet` row of a device.
+    pub fn device(&self, sid: &str) -> Option<Value> {
+        self.panel()
+            .device_mut(sid)
+            .map(|row| Value::Object(row.clone()))
+    }
+
+    /// Add a `historyGet` row, e.g. `json!({"d": 1700000000, "a": 1, "e": 12})`.
+    pub fn add_history(&self, row: Value) {
+        self.panel().history.push(row);
+    }
+
+    /// End the session now, as if it had timed out.
+    pub fn expire_session(&self) {
+        self.panel().session_expired = true;
+    }
+
+    /// End the session after this much inactivity. Defaults to 10 minutes.
+    pub fn set_session_timeout(&self, timeout: Duration) {
+        self.panel().session_timeout = timeout;
+    }
+
+    /// Reject tokens this long after `tokenGet` issued them. Defaults to 10
+    /// minutes.
+    pub fn set_token_lifetime(&self, lifetime: Duration) {
+        self.panel().token_lifetime = lifetime;
+    }
+
+    /// The number of requests the panel received, including rejected ones.
+    pub async fn request_count(&self) -> usize {
+        self.server
+            .received_requests()
+            .await
+            .map_or(0, |requests| requests.len())
+    }
+
+    fn panel(&self) -> std::sync::MutexGuard<'_, Panel> {
+        self.panel.lock().expect("simulator lock poisoned")
+    }
+}
+
+/// The state of the simulated panel
+struct Panel {
+    areas: BTreeMap<u8, Mode>,
+    alerts: BTreeMap<String, String>,
+    devices: Vec<Value>,
+    history: Vec<Value>,
+    session_timeout: Duration,
+    session_expired: bool,
+    last_request: Instant,
+    token_lifetime: Duration,
+    tokens: HashMap<String, Instant>,
+    next_token: u64,
+}
+
+impl Panel {
+    fn new() -> Panel {
+        Panel {
+            areas: BTreeMap::from([(1, Mode::Disarmed), (2, Mode::Disarmed)]),
+            alerts: BTreeMap::new(),
+            devices: Vec::new(),
+            history: Vec::new(),
+            session_timeout: Duration::from_secs(600),
+            session_expired: false,
+            last_request: Instant::now(),
+            token_lifetime: Duration::from_secs(600),
+            tokens: HashMap::new(),
+            next_token: 1,
+        }
+    }
+
+    fn handle(&mut self, request: &Request) -> ResponseTemplate {
+        let now = Instant::now();
+        if self.session_expired || now - self.last_request > self.session_timeout {
+            // The next request starts a new session, invalidating all tokens
+            self.session_expired = false;
+            self.tokens.clear();
+            self.last_request = now;
+            return login_page();
+        }
+        self.last_request = now;
+
+        let action = request.url.path().trim_start_matches("/action/");
+        if request.method == "GET" {
+            return match action {
+                "tokenGet" => self.issue_token(now),
+                "panelCondGet" => self.condition(),
+                "deviceListGet" => {
+                    ResponseTemplate::new(200).set_body_json(json!({ "senrows": self.devices }))
+                }
+                "historyGet" => {
+                    ResponseTemplate::new(200).set_body_json(json!({ "hisrows": self.history }))
+                }
+                _ => ResponseTemplate::new(404),
+            };
+        }
+
+        let token_valid = request
+            .headers
+            .get("x-token")
+            .and_then(|token| token.to_str().ok())
+            .and_then(|token| self.tokens.get(token))
+            .is_some_and(|issued| now - *issued < self.token_lifetime);
+        if !token_valid {
+            return login_page();
+        }
+
+        let form: HashMap<String, String> = url::form_urlencoded::parse(&request.body)
+            .into_owned()
+            .collect();
+        let result = match action {
+            "panelCondPost" => self.change_mode(&form),
+            "deviceSwitchPSSPost" => self.update_from_form(&form, &[("switch", "status_ex")]),
+            "deviceThermostatPost" => self.update_from_form(
+                &form,
+                &[("set_temp", "set_temp"), ("thermo_mode", "thermo_mode")],
+            ),
+            "deviceShutterPost" => self.move_shutter(&form),
+            "deviceDimmerPost" => self.update_from_form(&form, &[("level", "level")]),
+            _ => return ResponseTemplate::new(404),
+        };
+
+        let (result, message) = match result {
+            Ok(()) => (1, String::new()),
+            Err(message) => (0, message),
+        };
+        ResponseTemplate::new(200).set_body_json(json!({ "result": result, "message": message }))
+    }
+
+    fn issue_token(&mut self, now: Instant) -> ResponseTemplate {
+        let token = format!("{:016x}", self.next_token);
+        self.next_token += 1;
+        self.tokens.insert(token.clone(), now);
+        ResponseTemplate::new(200).set_body_json(json!({ "result": 1, "message": token }))
+    }
+
+    fn condition(&self) -> ResponseTemplate {
+        let forms: serde_json::Map<_, _> = self
+            .areas
+            .iter()
+            .map(|(area, mode)| {
+                (
+                    format!("pcondform{area}"),
+                    json!({ "mode": u8::from(*mode), "alarm": 0 }),
+                )
+            })
+            .collect();
+        ResponseTemplate::new(200).set_body_json(json!({ "forms": forms, "updates": self.alerts }))
+    }
+
+    fn change_mode(&mut self, form: &HashMap<String, String>) -> Result<(), String> {
+        let mode = form
+            .get("mode")
+            .and_then(|mode| mode.parse::<u8>().ok())
+            .map(Mode::from)
+            .filter(|mode| !matches!(mode, Mode::Unknown(_)))
+            .ok_or("invalid mode")?;
+        let area = form
+            .get("area")
+            .and_then(|area| area.parse::<u8>().ok())
+            .filter(|area| self.areas.contains_key(area))
+            .ok_or("invalid area")?;
+
+        // The panel accepts the request but stays disarmed while a door is open
+        let open_contact = self.devices.iter().any(|device| {
+            number(&device["area"]) == Some(area)
+                && number(&device["type"]).is_some_and(|kind| DeviceKind::from(kind).is_contact())
+                && number(&device["status_ex"]) == Some(1)
+        });
+        if mode == Mode::Disarmed || !open_contact {
+            self.areas.insert(area, mode);
+        }
+
+        Ok(())
+    }
+
+    fn move_shutter(&mut self, form: &HashMap<String, String>) -> Result<(), String> {
+        let level = match form.get("action").map(String::as_str) {
+            Some("open") => "100",
+            Some("close") => "0",
+            Some("stop") => return self.device_of(form).map(drop),
+            _ => return self.update_from_form(form, &[("level", "level")]),
+        };
+        self.device_of(form)?.insert("level".into(), level.into());
+        Ok(())
+    }
+
+    /// Copy the `form` fields to the device fields they are paired with.
+    fn update_from_form(
+        &mut self,
+        form: &HashMap<String, String>,
+        fields: &[(&str, &str)],
+    ) -> Result<(), String> {
+        let device = self.device_of(form)?;
+        for (key, field) in fields {
+            if let Some(value) = form.get(*key) {
+                device.insert((*field).into(), value.as_str().into());
+            }
+        }
+        Ok(())
+    }
+
+    /// The device named by the `id` form field.
+    fn device_of(
+        &mut self,
+        form: &HashMap<String, String>,
+    ) -> Result<&mut serde_json::Map<String, Value>, String> {
+        let sid = form.get("id").ok_or("missing device id")?;
+        self.device_mut(sid)
+            .ok_or_else(|| format!("unknown device {sid}"))
+    }
+
+    fn device_mut(&mut self, sid: &str) -> Option<&mut serde_json::Map<String, Value>> {
+        self.devices
+            .iter_mut()
+            .filter_map(Value::as_object_mut)
+            .find(|device| device.get("sid").and_then(Value::as_str) == Some(sid))
+    }
+}
+
+/// A number the panel reports either as a JSON number or a string.
+fn number(value: &Value) -> Option<u8> {
+    match value {
+        Value::Number(number) => number.as_u64()?.try_into().ok(),
+        Value::String(text) => text.parse().ok(),
+        _ => None,
+    }
+}
+
+fn login_page() -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_raw(LOGIN_PAGE, "text/html")
+}
+
+/// Answers authenticated requests from the panel state
+struct Handler(Arc<Mutex<Panel>>);
+
+impl Respond for Handler {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        self.0
+            .lock()
+            .expect("simulator lock poisoned")
+            .handle(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Error, Mode, State};
+
+    #[tokio::test]
+    async fn modes_persist() {
+        let panel = Simulator::start("admin", "changeme").await;
+        let client = panel.client().build().unwrap();
+
+        client.change_mode(Area::Area2, Mode::Home1).await.unwrap();
+        let modes = client.get_status().await.unwrap();
+        assert_eq!(modes.mode(Area::Area1), Some(Mode::Disarmed));
+        assert_eq!(modes.mode(Area::Area2), Some(Mode::Home1));
+
+        let result = client.change_mode(Area::Other(3), Mode::Armed).await;
+        assert!(matches!(result.unwrap_err(), Error::Panel(_)));
+    }
+
+    #[tokio::test]
+    async fn open_door_keeps_area_disarmed() {
+        let panel = Simulator::start("admin", "changeme").await;
+        panel.add_device("RF:01", DeviceKind::DoorContact, "Front Door", Area::Area1);
+        panel.update_device("RF:01", json!({ "status_ex": 1 }));
+
+        let client = panel
+            .client()
+            .verify_mode_change(Duration::from_millis(100))
+            .build()
+            .unwrap();
+        let devices = client.list_devices().await.unwrap();
+        assert_eq!(devices[0].state, State::Open);
+
+        let result = client.change_mode(Area::Area1, Mode::Armed).await;
+        assert!(matches!(result.unwrap_err(), Error::ModeNotReached { .. }));
+        assert_eq!(panel.mode(Area::Area1), Some(Mode::Disarmed));
+    }
+
+    #[tokio::test]
+    async fn sessions_and_tokens_expire() {
+        let panel = Simulator::start("admin", "changeme").await;
+        panel.add_device("RF:02", DeviceKind::PowerSwitch1, "Lamp", Area::Area1);
+        let client = panel.client().build().unwrap();
+
+        client.set_switch("RF:02", true).await.unwrap();
+        panel.expire_session();
+        client.set_switch("RF:02", false).await.unwrap();
+        assert_eq!(panel.device("RF:02").unwrap()["status_ex"], "0");
+
+        panel.set_token_lifetime(Duration::ZERO);
+        client.set_switch("RF:02", true).await.unwrap_err();
+        panel.set_token_lifetime(Duration::from_secs(600));
+        client.set_switch("RF:02", true).await.unwrap();
+        assert!(client.switch("RF:02").await.unwrap().on);
+    }
+
+    #[tokio::test]
+    async fn wrong_password_is_unauthorized() {
+        let panel = Simulator::start("admin", "changeme").await;
+        let client = Client::builder("admin", "wrong")
+            .base_url(panel.base_url())
+            .build()
+            .unwrap();
+
+        let result = client.get_status().await;
+        assert!(matches!(result.unwrap_err(), Error::Unauthorized));
+    }
+}
@@ -0,0 +1,591 @@
+use sha2::{Digest, Sha256};
+use tokio::sync::OnceCell;
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use crate::errors::{Error, Result};
+
+/// The SHA-256 fingerprint of a DER-encoded TLS certificate.
+///
+/// Parses from and displays as colon-separated hex, the format printed by
+/// `openssl x509 -noout -fingerprint -sha256`. Separators and case are
+/// ignored when parsing.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct Fingerprint([u8; 32]);
+
+impl Fingerprint {
+    /// Compute the fingerprint of a DER-encoded certificate.
+    #[must_use]
+    pub fn from_der(der: &[u8]) -> Fingerprint {
+        Fingerprint(Sha256::digest(der).into())
+    }
+
+    /// Compute the fingerprint of the first certificate in a PEM document.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the input does not contain a PEM certificate.
+    pub fn from_pem(pem: &[u8]) -> Result<Fingerprint> {
+        let pem = pem::parse(pem).map_err(|e| Error::InvalidCertificate(e.to_string()))?;
+
+        if pem.tag() != "CERTIFICATE" {
+            let msg = format!("expected a CERTIFICATE, found {}", pem.tag());
+            return Err(Error::InvalidCertificate(msg));
+        }
+
+        Ok(Fingerprint::from_der(pem.contents()))
+    }
+
+    /// The raw digest bytes.
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, byte) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(":")?;
+            }
+            write!(f, "{byte:02X}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Fingerprint {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Fingerprint> {
+        let invalid = || Error::InvalidCertificate(format!("invalid SHA-256 fingerprint: {s}"));
+
+        let hex: Vec<u8> = s.bytes().filter(|b| !matches!(b, b':' | b' ')).collect();
+
+        if hex.len() != 64 {
+            return Err(invalid());
+        }
+
+        let mut bytes = [0; 32];
+        for (byte, pair) in bytes.iter_mut().zip(hex.chunks(2)) {
+            let pair = std::str::from_utf8(pair).map_err(|_| invalid())?;
+            *byte = u8::from_str_radix(pair, 16).map_err(|_| invalid())?;
+        }
+
+        Ok(Fingerprint(bytes))
+    }
+}
+
+/// How the panel's certificate is pinned.
+#[derive(Debug)]
+pub(crate) enum Pin {
+    /// Only accept this exact certificate.
+    Fixed(Fingerprint),
+
+    /// Record the first certificate seen and refuse any other afterwards.
+    TrustOnFirstUse(Mutex<Option<Fingerprint>>),
+}
+
+impl Pin {
+    /// The pinned fingerprint, if one is known yet.
+    pub(crate) fn fingerprint(&self) -> Option<Fingerprint> {
+        match self {
+            Pin::Fixed(fingerprint) => Some(*fingerprint),
+            Pin::TrustOnFirstUse(seen) => *seen.lock().expect("pin lock poisoned"),
+        }
+    }
+
+    /// Check a DER-encoded certificate (`None` without TLS) against the pin.
+    pub(crate) fn verify_der(&self, der: Option<&[u8]>) -> Result {
+        let actual = der.map(Fingerprint::from_der);
+
+        let expected = match self {
+            Pin::Fixed(expected) => *expected,
+            Pin::TrustOnFirstUse(seen) => {
+                let mut seen = seen.lock().expect("pin lock poisoned");
+                match (*seen, actual) {
+                    (Some(expected), _) => expected,
+                    (None, Some(actual)) => *seen.insert(actual),
+                    (None, None) => {
+                        return Err(Error::CertificateMismatch {
+                            expected: None,
+                            actual: None,
+                        });
+                    }
+                }
+            }
+        };
+
+        if actual == Some(expected) {
+            Ok(())
+        } else {
+            Err(Error::CertificateMismatch {
+                expected: Some(expected),
+                actual,
+            })
+        }
+    }
+}
+
+/// The HTTP client used to reach the panel
+///
+/// With a pin, the certificate is checked during the TLS handshake of every
+/// connection, so credentials are never sent to a server presenting another
+/// certificate. rustls checks the pin itself. native-tls cannot, so the
+/// certificate is fetched once without credentials, checked against the pin
+/// and then trusted as the only root.
+pub(crate) struct HttpClient {
+    client: OnceCell<reqwest::Client>,
+    #[cfg(feature = "native-tls")]
+    pending: Option<Pending>,
+    #[cfg(feature = "rustls")]
+    verifier: Option<Arc<PinVerifier>>,
+}
+
+/// Configures everything but TLS on a new `reqwest` client builder
+pub(crate) type Configure = Box<dyn Fn() -> reqwest::ClientBuilder + Send + Sync>;
+
+impl HttpClient {
+    /// Build the client; `url` is where the certificate is fetched from if it
+    /// cannot be checked during the handshake.
+    pub(crate) fn new(
+        configure: Configure,
+        accept_invalid_certs: bool,
+        pin: Option<Arc<Pin>>,
+        url: &reqwest::Url,
+    ) -> Result<HttpClient> {
+        match pin {
+            Some(pin) => HttpClient::pinned(configure, pin, url),
+            None => {
+                let client = configure()
+                    .danger_accept_invalid_certs(accept_invalid_certs)
+                    .build()?;
+                Ok(HttpClient {
+                    client: OnceCell::new_with(Some(client)),
+                    #[cfg(feature = "native-tls")]
+                    pending: None,
+                    #[cfg(feature = "rustls")]
+                    verifier: None,
+                })
+            }
+        }
+    }
+
+    #[cfg(feature = "rustls")]
+    fn pinned(configure: Configure, pin: Arc<Pin>, _url: &reqwest::Url) -> Result<HttpClient> {
+        let provider = rustls::crypto::CryptoProvider::get_default()
+            .cloned()
+            .unwrap_or_else(|| Arc::new(rustls::crypto::aws_lc_rs::default_provider()));
+        let verifier = Arc::new(PinVerifier {
+            pin,
+            algorithms: provider.signature_verification_algorithms,
+        });
+
+        let config = rustls::ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .expect("the crypto provider should support the default TLS versions")
+            .dangerous()
+            .with_custom_certificate_verifier(verifier.clone())
+            .with_no_client_auth();
+
+        Ok(HttpClient {
+            client: OnceCell::new_with(Some(
+                configure()
+                    .tls_backend_preconfigured(config)
+                    .https_only(true)
+                    .build()?,
+            )),
+            verifier: Some(verifier),
+        })
+    }
+
+    #[cfg(feature = "native-tls")]
+    fn pinned(configure: Configure, pin: Arc<Pin>, url: &reqwest::Url) -> Result<HttpClient> {
+        let probe = configure()
+            .danger_accept_invalid_certs(true)
+            .tls_info(true)
+            .build()?;
+
+        Ok(HttpClient {
+            client: OnceCell::new(),
+            pending: Some(Pending {
+                configure,
+                pin,
+                probe,
+                url: url.clone(),
+            }),
+        })
+    }
+
+    /// The client, fetching and checking the pinned certificate first if
+    /// necessary.
+    pub(crate) async fn get(&self) -> Result<&reqwest::Client> {
+        #[cfg(feature = "native-tls")]
+        if let Some(pending) = &self.pending {
+            return self.client.get_or_try_init(|| pending.build()).await;
+        }
+
+        Ok(self
+            .client
+            .get()
+            .expect("the client should be built unless it is pending"))
+    }
+
+    /// Convert an error sending a request, reporting rejected certificates
+    /// as [`Error::CertificateMismatch`].
+    pub(crate) fn error(&self, error: reqwest::Error) -> Error {
+        #[cfg(feature = "rustls")]
+        if let Some(verifier) = &self.verifier {
+            if let Some(Rejected(actual)) = Rejected::find(&error) {
+                return Error::CertificateMismatch {
+                    expected: verifier.pin.fingerprint(),
+                    actual: Some(*actual),
+                };
+            }
+
+            // Plain HTTP is refused before connecting, as there is no
+            // certificate to check.
+            if error.is_builder() {
+                return Error::CertificateMismatch {
+                    expected: verifier.pin.fingerprint(),
+                    actual: None,
+                };
+            }
+        }
+
+        error.into()
+    }
+}
+
+/// What is needed to build a native-tls client trusting only the pinned
+/// certificate
+#[cfg(feature = "native-tls")]
+struct Pending {
+    configure: Configure,
+    pin: Arc<Pin>,
+    probe: reqwest::Client,
+    url: reqwest::Url,
+}
+
+#[cfg(feature = "native-tls")]
+impl Pending {
+    async fn build(&self) -> Result<reqwest::Client> {
+        let res = self.probe.get(self.url.clone()).send().await?;
+        let der = res
+            .extensions()
+            .get::<reqwest::tls::TlsInfo>()
+            .and_then(reqwest::tls::TlsInfo::peer_certificate);
+        self.pin.verify_der(der)?;
+
+        let der = der.expect("a verified certificate should be present");
+        let certificate = reqwest::Certificate::from_der(der)?;
+
+        Ok((self.configure)()
+            .tls_certs_only([certificate])
+            .danger_accept_invalid_hostnames(true)
+            .build()?)
+    }
+}
+
+/// Accepts exactly the pinned certificate, for any hostname
+#[cfg(feature = "rustls")]
+#[derive(Debug)]
+struct PinVerifier {
+    pin: Arc<Pin>,
+    algorithms: rustls::crypto::WebPkiSupportedAlgorithms,
+}
+
+/// The fingerprint of a certificate refused by [`PinVerifier`], carried in
+/// the handshake error so that it is reported for the failing connection
+#[cfg(feature = "rustls")]
+#[derive(Debug)]
+struct Rejected(Fingerprint);
+
+#[cfg(feature = "rustls")]
+impl Rejected {
+    /// Find the refused certificate in the sources of a request error.
+    fn find(error: &reqwest::Error) -> Option<&Rejected> {
+        let mut source: Option<&(dyn std::error::Error + 'static)> = Some(error);
+        while let Some(error) = source {
+            if let Some(rustls::Error::InvalidCertificate(rustls::CertificateError::Other(other))) =
+                error.downcast_ref::<rustls::Error>()
+            {
+                return other.0.downcast_ref::<Rejected>();
+            }
+
+            // `io::Error` hides the error it wraps from `source()`
+            source = match error.downcast_ref::<std::io::Error>() {
+                Some(error) => error
+                    .get_ref()
+                    .map(|error| error as &(dyn std::error::Error + 'static)),
+                None => error.source(),
+            };
+        }
+        None
+    }
+}
+
+#[cfg(feature = "rustls")]
+impl fmt::Display for Rejected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "certificate {} does not match the pin", self.0)
+    }
+}
+
+#[cfg(feature = "rustls")]
+impl std::error::Error for Rejected {}
+
+#[cfg(feature = "rustls")]
+impl rustls::client::danger::ServerCertVerifier for PinVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &rustls::pki_types::CertificateDer<'_>,
+        _intermediates: &[rustls::pki_types::CertificateDer<'_>],
+        _server_name: &rustls::pki_types::ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: rustls::pki_types::UnixTime,
+    ) -> std::result::Result<rustls::client::danger::ServerCertVerified, rustls::Error> {
+        match self.pin.verify_der(Some(end_entity)) {
+            Ok(()) => Ok(rustls::client::danger::ServerCertVerified::assertion()),
+            Err(_) => Err(rustls::Error::InvalidCertificate(
+                rustls::CertificateError::Other(rustls::OtherError(Arc::new(Rejected(
+                    Fingerprint::from_der(end_entity),
+                )))),
+            )),
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &rustls::pki_types::CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> std::result::Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &rustls::pki_types::CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> std::result::Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
+        self.algorithms.supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DER: &[u8] = b"not really a certificate";
+
+    #[test]
+    fn fingerprint_roundtrip() {
+        let fingerprint = Fingerprint::from_der(DER);
+        let parsed: Fingerprint = fingerprint.to_string().parse().unwrap();
+        assert_eq!(parsed, fingerprint);
+        assert_eq!(fingerprint.to_string().len(), 95);
+    }
+
+    #[test]
+    fn fingerprint_parse_without_separators() {
+        let hex = "ab".repeat(32);
+        let fingerprint: Fingerprint = hex.parse().unwrap();
+        assert_eq!(fingerprint.as_bytes(), &[0xAB; 32]);
+    }
+
+    #[test]
+    fn fingerprint_parse_invalid() {
+        assert!("abcd".parse::<Fingerprint>().is_err());
+        assert!("zz".repeat(32).parse::<Fingerprint>().is_err());
+    }
+
+    #[test]
+    fn fingerprint_from_pem() {
+        let pem = pem::encode(&pem::Pem::new("CERTIFICATE", DER));
+        let fingerprint = Fingerprint::from_pem(pem.as_bytes()).unwrap();
+        assert_eq!(fingerprint, Fingerprint::from_der(DER));
+    }
+
+    #[test]
+    fn fingerprint_from_pem_wrong_tag() {
+        let pem = pem::encode(&pem::Pem::new("PRIVATE KEY", DER));
+        assert!(Fingerprint::from_pem(pem.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn fixed_pin() {
+        let pin = Pin::Fixed(Fingerprint::from_der(DER));
+        assert!(pin.verify_der(Some(DER)).is_ok());
+        assert!(matches!(
+            pin.verify_der(Some(b"other")),
+            Err(Error::CertificateMismatch { .. })
+        ));
+        assert!(pin.verify_der(None).is_err());
+    }
+
+    #[test]
+    fn trust_on_first_use_records_first_certificate() {
+        let pin = Pin::TrustOnFirstUse(Mutex::new(None));
+        assert!(pin.fingerprint().is_none());
+
+        pin.verify_der(Some(DER)).unwrap();
+        assert_eq!(pin.fingerprint(), Some(Fingerprint::from_der(DER)));
+
+        assert!(pin.verify_der(Some(DER)).is_ok());
+        assert!(pin.verify_der(Some(b"other")).is_err());
+    }
+
+    /// A panel served over TLS with a self-signed certificate that can be
+    /// swapped, answering each request on a new connection
+    struct TlsPanel {
+        url: String,
+        config: Arc<Mutex<Arc<tokio_rustls::rustls::ServerConfig>>>,
+        credentials_seen: Arc<std::sync::atomic::AtomicBool>,
+    }
+
+    /// A self-signed certificate, its fingerprint and server config
+    fn certificate() -> (Fingerprint, Arc<tokio_rustls::rustls::ServerConfig>) {
+        use tokio_rustls::rustls;
+
+        let key = rcgen::generate_simple_self_signed(["panel".to_owned()]).unwrap();
+        let der = key.cert.der().clone();
+        let private_key =
+            rustls::pki_types::PrivateKeyDer::Pkcs8(key.signing_key.serialize_der().into());
+        let provider = Arc::new(rustls::crypto::aws_lc_rs::default_provider());
+        let config = rustls::ServerConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(vec![der.clone()], private_key)
+            .unwrap();
+
+        (Fingerprint::from_der(&der), Arc::new(config))
+    }
+
+    impl TlsPanel {
+        async fn start(config: Arc<tokio_rustls::rustls::ServerConfig>) -> TlsPanel {
+            use std::sync::atomic::Ordering;
+            use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let panel = TlsPanel {
+                url: format!("https://{}/action/", listener.local_addr().unwrap()),
+                config: Arc::new(Mutex::new(config)),
+                credentials_seen: Arc::default(),
+            };
+
+            let config = Arc::clone(&panel.config);
+            let credentials_seen = Arc::clone(&panel.credentials_seen);
+            tokio::spawn(async move {
+                loop {
+                    let (tcp, _) = listener.accept().await.unwrap();
+                    let acceptor = tokio_rustls::TlsAcceptor::from(config.lock().unwrap().clone());
+                    let credentials_seen = Arc::clone(&credentials_seen);
+                    tokio::spawn(async move {
+                        let Ok(mut tls) = acceptor.accept(tcp).await else {
+                            return;
+                        };
+
+                        let mut request = Vec::new();
+                        let mut buf = [0; 1024];
+                        while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                            match tls.read(&mut buf).await {
+                                Ok(0) | Err(_) => return,
+                                Ok(n) => request.extend_from_slice(&buf[..n]),
+                            }
+                        }
+                        let request = String::from_utf8_lossy(&request).to_ascii_lowercase();
+                        if request.contains("authorization:") {
+                            credentials_seen.store(true, Ordering::SeqCst);
+                        }
+
+                        let body = r#"{"forms": {"pcondform1": {"mode": 0}}}"#;
+                        let response = format!(
+                            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                            body.len()
+                        );
+                        let _ = tls.write_all(response.as_bytes()).await;
+                        let _ = tls.shutdown().await;
+                    });
+                }
+            });
+
+            panel
+        }
+
+        fn credentials_seen(&self) -> bool {
+            self.credentials_seen
+                .swap(false, std::sync::atomic::Ordering::SeqCst)
+        }
+    }
+
+    #[tokio::test]
+    async fn pinned_certificate_is_accepted() {
+        let (fingerprint, config) = certificate();
+        let panel = TlsPanel::start(config).await;
+
+        let client = crate::Client::builder("user", "pass")
+            .base_url(&panel.url)
+            .pin_certificate(fingerprint)
+            .build()
+            .unwrap();
+        client.get_status().await.unwrap();
+        assert!(panel.credentials_seen());
+    }
+
+    #[tokio::test]
+    async fn mismatched_certificate_gets_no_credentials() {
+        let (served, config) = certificate();
+        let (other, _) = certificate();
+        let panel = TlsPanel::start(config).await;
+
+        let client = crate::Client::builder("user", "pass")
+            .base_url(&panel.url)
+            .pin_certificate(other)
+            .build()
+            .unwrap();
+
+        // Concurrent handshakes each report the certificate they were sent
+        let (a, b) = tokio::join!(client.get_status(), client.get_status());
+        for result in [a, b] {
+            assert!(matches!(
+                result,
+                Err(Error::CertificateMismatch { expected, actual })
+                    if expected == Some(other) && actual == Some(served)
+            ));
+        }
+        assert!(!panel.credentials_seen());
+    }
+
+    #[tokio::test]
+    async fn changed_certificate_gets_no_credentials() {
+        let (fingerprint, config) = certificate();
+        let (_, other) = certificate();
+        let panel = TlsPanel::start(config).await;
+
+        let client = crate::Client::builder("user", "pass")
+            .base_url(&panel.url)
+            .trust_on_first_use(None)
+            .build()
+            .unwrap();
+        client.get_status().await.unwrap();
+        assert!(panel.credentials_seen());
+        assert_eq!(client.certificate_fingerprint(), Some(fingerprint));
+
+        // Every response closes the connection, so the next request
+        // connects again and sees the new certificate.
+        *panel.config.lock().unwrap() = other;
+        assert!(client.get_status().await.is_err());
+        assert!(!panel.credentials_seen());
+        assert_eq!(client.certificate_fingerprint(), Some(fingerprint));
+    }
+}
@@ -1,6 +1,6 @@
 /// Implement `Serialize` (as variant name) and `Deserialize` (from integer or
 /// numeric string) for one or more `#[repr(u8)]` enums that derive `Display`
-/// and `TryFromPrimitive`.
+/// and `TryFromPrimitive` (or `FromPrimitive` with a catch-all variant).
 macro_rules! impl_numeric_serde {
     ($($T:ty),+ $(,)?) => { $(
         impl serde::Serialize for $T {
@@ -17,6 +17,17 @@ macro_rules! impl_numeric_serde {
     )+ };
 }
 
+/// Emit a `tracing` event at the given level, e.g.
+/// `trace_event!(debug, action, "session expired")`. Expands to nothing
+/// without the `tracing` feature, so the arguments must not be the only use
+/// of a value.
+macro_rules! trace_event {
+    ($level:ident, $($arg:tt)+) => {
+        #[cfg(feature = "tracing")]
+        tracing::$level!($($arg)+);
+    };
+}
+
 /// Deserialize an enum from a numeric value (integer or numeric string).
 ///
 /// The target type must implement `TryFrom<u8>` (e.g. via
@@ -56,3 +67,82 @@ where
 
     deserializer.deserialize_any(NumVisitor(PhantomData))
 }
+
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum NumberOrString {
+    Number(u64),
+    String(String),
+}
+
+/// Deserialize an unsigned integer from a number or a numeric string.
+pub(crate) fn deserialize_number<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: TryFrom<u64>,
+    <T as TryFrom<u64>>::Error: std::fmt::Display,
+    D: serde::Deserializer<'de>,
+{
+    deserialize_optional_number(deserializer)?
+        .ok_or_else(|| serde::de::Error::custom("expected a number, found an empty string"))
+}
+
+/// Deserialize an optional unsigned integer from a number or a numeric
+/// string. The panel uses empty strings for missing values.
+pub(crate) fn deserialize_optional_number<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: TryFrom<u64>,
+    <T as TryFrom<u64>>::Error: std::fmt::Display,
+    D: serde::Deserializer<'de>,
+{
+    use serde::de::Error;
+
+    let value = match serde::Deserialize::deserialize(deserializer)? {
+        NumberOrString::Number(n) => n,
+        NumberOrString::String(s) if s.trim().is_empty() => return Ok(None),
+        NumberOrString::String(s) => s.trim().parse().map_err(D::Error::custom)?,
+    };
+
+    T::try_from(value).map(Some).map_err(D::Error::custom)
+}
+
+/// Deserialize an optional string, mapping empty strings to `None`.
+pub(crate) fn deserialize_optional_string<'de, D>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value: Option<String> = serde::Deserialize::deserialize(deserializer)?;
+    Ok(value.filter(|s| !s.is_empty()))
+}
+
+/// Deserialize a boolean from `0`/`1`, given as number or numeric string.
+pub(crate) fn deserialize_flag<'de, D>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    deserialize_number::<u8, D>(deserializer).map(|flag| flag != 0)
+}
+
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum FloatOrString {
+    Float(f64),
+    String(String),
+}
+
+/// Deserialize an optional float from a number or a numeric string, mapping
+/// empty strings to `None`.
+pub(crate) fn deserialize_optional_float<'de, D>(deserializer: D) -> Result<Option<f64>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::de::Error;
+
+    match serde::Deserialize::deserialize(deserializer)? {
+        Some(FloatOrString::Float(f)) => Ok(Some(f)),
+        Some(FloatOrString::String(s)) if s.trim().is_empty() => Ok(None),
+        Some(FloatOrString::String(s)) => s.trim().parse().map(Some).map_err(D::Error::custom),
+        None => Ok(None),
+    }
+}
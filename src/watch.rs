@@ -0,0 +1,451 @@
+use futures::Stream;
+use serde::Serialize;
+
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+use crate::client::Client;
+use crate::constants::{Area, DeviceKind, Mode, State, Status};
+use crate::errors::Result;
+use crate::resources::devices::Device;
+use crate::resources::panel::{AreaCondition, Modes};
+
+/// The longest delay between polls while the panel is unreachable
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+/// A change detected by [`Client::watch`]
+///
+/// Serializes with the variant name in an `event` field, e.g.
+/// `{"event": "ModeChanged", "area": "Area1", "from": "Disarmed", "to": "Armed"}`.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "event")]
+pub enum Event {
+    /// The mode of an area changed (`from` is `None` for a newly reported
+    /// area)
+    ModeChanged {
+        /// The area
+        area: Area,
+        /// The previous mode
+        from: Option<Mode>,
+        /// The new mode
+        to: Mode,
+    },
+
+    /// An alarm was raised or cleared in an area, or an entry or exit delay
+    /// started or ended. Delays counting down do not cause events.
+    AlarmChanged {
+        /// The area
+        area: Area,
+        /// Whether an alarm is in progress
+        alarm: bool,
+        /// Seconds left before an alarm is raised after an entry was detected
+        entry_delay: Option<u32>,
+        /// Seconds left until the area is armed
+        exit_delay: Option<u32>,
+    },
+
+    /// A panel-wide fault or alert message appeared, changed or was cleared
+    AlertChanged {
+        /// The field name the panel uses for the alert
+        alert: String,
+        /// The new message (`None` once the alert is cleared)
+        message: Option<String>,
+    },
+
+    /// A device reported a new state, e.g. a door was opened or closed
+    StateChanged {
+        /// The sensor ID
+        sid: String,
+        /// The device name
+        name: String,
+        /// The new state
+        state: State,
+    },
+
+    /// The battery status of a device changed
+    BatteryChanged {
+        /// The sensor ID
+        sid: String,
+        /// The device name
+        name: String,
+        /// The new battery status ([`Status::Error`] means low)
+        battery: Status,
+    },
+
+    /// The tamper status of a device changed
+    TamperChanged {
+        /// The sensor ID
+        sid: String,
+        /// The device name
+        name: String,
+        /// The new tamper status ([`Status::Error`] means tampered)
+        tamper: Status,
+    },
+
+    /// The condition of a device changed
+    ConditionChanged {
+        /// The sensor ID
+        sid: String,
+        /// The device name
+        name: String,
+        /// The new condition ([`Status::Error`] means faulty)
+        condition: Status,
+    },
+
+    /// A device was added to the panel
+    DeviceAdded {
+        /// The sensor ID
+        sid: String,
+        /// The device name
+        name: String,
+        /// The device kind
+        kind: DeviceKind,
+    },
+
+    /// A device was removed from the panel
+    DeviceRemoved {
+        /// The sensor ID
+        sid: String,
+        /// The device name
+        name: String,
+    },
+}
+
+struct Watch {
+    client: Client,
+    interval: Duration,
+    modes: Option<Modes>,
+    devices: Option<HashMap<String, Device>>,
+    pending: VecDeque<Event>,
+    failures: u32,
+    first: bool,
+}
+
+impl Watch {
+    async fn next(&mut self) -> Result<Event> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Ok(event);
+            }
+
+            if !self.first {
+                tokio::time::sleep(self.delay()).await;
+            }
+            self.first = false;
+
+            let snapshot = futures::try_join!(self.client.get_status(), self.client.list_devices());
+            let (modes, devices) = match snapshot {
+                Ok(snapshot) => snapshot,
+                Err(e) => {
+                    self.failures += 1;
+                    return Err(e);
+                }
+            };
+            self.failures = 0;
+
+            let devices = devices
+                .into_iter()
+                .map(|device| (device.sid.clone(), device))
+                .collect();
+
+            if let Some(previous) = &self.modes {
+                self.pending.extend(diff_modes(previous, &modes));
+            }
+            if let Some(previous) = &self.devices {
+                self.pending.extend(diff_devices(previous, &devices));
+            }
+
+            self.modes = Some(modes);
+            self.devices = Some(devices);
+        }
+    }
+
+    /// The poll interval, doubled for every consecutive failure.
+    fn delay(&self) -> Duration {
+        let factor = 2_u32.saturating_pow(self.failures.min(16));
+        self.interval
+            .saturating_mul(factor)
+            .min(MAX_BACKOFF.max(self.interval))
+    }
+}
+
+pub(crate) fn watch(
+    client: Client,
+    interval: Duration,
+) -> impl Stream<Item = Result<Event>> + Send {
+    let state = Watch {
+        client,
+        interval,
+        modes: None,
+        devices: None,
+        pending: VecDeque::new(),
+        failures: 0,
+        first: true,
+    };
+
+    futures::stream::unfold(state, |mut state| async move {
+        let event = state.next().await;
+        Some((event, state))
+    })
+}
+
+fn diff_modes(previous: &Modes, current: &Modes) -> Vec<Event> {
+    let mut events = Vec::new();
+
+    for condition in &current.areas {
+        let old = previous.get(condition.area);
+
+        let from = old.map(|old| old.mode);
+        if from != Some(condition.mode) {
+            events.push(Event::ModeChanged {
+                area: condition.area,
+                from,
+                to: condition.mode,
+            });
+        }
+
+        if alarm_state(old) != alarm_state(Some(condition)) {
+            events.push(Event::AlarmChanged {
+                area: condition.area,
+                alarm: condition.alarm,
+                entry_delay: condition.entry_delay,
+                exit_delay: condition.exit_delay,
+            });
+        }
+    }
+
+    let mut alerts: Vec<_> = current
+        .alerts
+        .keys()
+        .chain(previous.alerts.keys())
+        .collect();
+    alerts.sort();
+    alerts.dedup();
+
+    events.extend(alerts.into_iter().filter_map(|alert| {
+        let message = current.alerts.get(alert);
+        (previous.alerts.get(alert) != message).then(|| Event::AlertChanged {
+            alert: alert.clone(),
+            message: message.cloned(),
+        })
+    }));
+
+    events
+}
+
+/// Whether an alarm, an entry delay and an exit delay are in progress.
+fn alarm_state(condition: Option<&AreaCondition>) -> (bool, bool, bool) {
+    condition.map_or((false, false, false), |condition| {
+        (
+            condition.alarm,
+            condition.entry_delay.is_some(),
+            condition.exit_delay.is_some(),
+        )
+    })
+}
+
+fn diff_devices(
+    previous: &HashMap<String, Device>,
+    current: &HashMap<String, Device>,
+) -> Vec<Event> {
+    let mut events = Vec::new();
+
+    let mut sids: Vec<_> = current.keys().collect();
+    sids.sort();
+
+    for sid in sids {
+        let device = &current[sid];
+        let (sid, name) = (device.sid.clone(), device.name.clone());
+
+        let Some(old) = previous.get(&device.sid) else {
+            events.push(Event::DeviceAdded {
+                sid,
+                name,
+                kind: device.kind,
+            });
+            continue;
+        };
+
+        if old.state != device.state {
+            events.push(Event::StateChanged {
+                sid: sid.clone(),
+                name: name.clone(),
+                state: device.state,
+            });
+        }
+        if old.battery != device.battery {
+            events.push(Event::BatteryChanged {
+                sid: sid.clone(),
+                name: name.clone(),
+                battery: device.battery,
+            });
+        }
+        if old.tamper != device.tamper {
+            events.push(Event::TamperChanged {
+                sid: sid.clone(),
+                name: name.clone(),
+                tamper: device.tamper,
+            });
+        }
+        if old.condition != device.condition {
+            events.push(Event::ConditionChanged {
+                sid,
+                name,
+                condition: device.condition,
+            });
+        }
+    }
+
+    let mut removed: Vec<_> = previous
+        .values()
+        .filter(|device| !current.contains_key(&device.sid))
+        .collect();
+    removed.sort_by(|a, b| a.sid.cmp(&b.sid));
+
+    events.extend(removed.into_iter().map(|device| Event::DeviceRemoved {
+        sid: device.sid.clone(),
+        name: device.name.clone(),
+    }));
+
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(sid: &str, state: u8, battery: u8) -> (String, Device) {
+        let device: Device = serde_json::from_value(serde_json::json!({
+            "sid": sid,
+            "type": 4,
+            "name": format!("Door {sid}"),
+            "area": 1,
+            "status_ex": state,
+            "cond_ok": 1,
+            "battery_ok": battery,
+            "tamper_ok": 1
+        }))
+        .unwrap();
+        (sid.to_owned(), device)
+    }
+
+    fn modes(area1: u8, area2: u8) -> Modes {
+        condition(serde_json::json!({
+            "forms": {
+                "pcondform1": { "mode": area1 },
+                "pcondform2": { "mode": area2 }
+            }
+        }))
+    }
+
+    fn condition(json: serde_json::Value) -> Modes {
+        use crate::resources::ApiResponse;
+
+        serde_json::from_value::<crate::resources::panel::Condition>(json)
+            .unwrap()
+            .into_result()
+            .unwrap()
+    }
+
+    #[test]
+    fn mode_changes() {
+        let events = diff_modes(&modes(0, 0), &modes(1, 0));
+        assert_eq!(
+            events,
+            [Event::ModeChanged {
+                area: Area::Area1,
+                from: Some(Mode::Disarmed),
+                to: Mode::Armed
+            }]
+        );
+        assert!(diff_modes(&modes(1, 0), &modes(1, 0)).is_empty());
+    }
+
+    #[test]
+    fn alarm_changes() {
+        let area = |alarm: u8, entry_delay: u32| {
+            condition(serde_json::json!({
+                "forms": {
+                    "pcondform1": { "mode": 1, "alarm": alarm, "entry_delay": entry_delay }
+                }
+            }))
+        };
+
+        assert_eq!(
+            diff_modes(&area(0, 0), &area(0, 30)),
+            [Event::AlarmChanged {
+                area: Area::Area1,
+                alarm: false,
+                entry_delay: Some(30),
+                exit_delay: None
+            }]
+        );
+        assert!(diff_modes(&area(0, 30), &area(0, 25)).is_empty());
+        assert_eq!(
+            diff_modes(&area(0, 5), &area(1, 0)),
+            [Event::AlarmChanged {
+                area: Area::Area1,
+                alarm: true,
+                entry_delay: None,
+                exit_delay: None
+            }]
+        );
+    }
+
+    #[test]
+    fn alert_changes() {
+        let alerts = |tamper: &str, power: &str| {
+            condition(serde_json::json!({
+                "forms": { "pcondform1": { "mode": 0 } },
+                "updates": { "tamper_status": tamper, "power_status": power }
+            }))
+        };
+
+        assert_eq!(
+            diff_modes(&alerts("Hallway", ""), &alerts("", "Power failure")),
+            [
+                Event::AlertChanged {
+                    alert: "power_status".into(),
+                    message: Some("Power failure".into())
+                },
+                Event::AlertChanged {
+                    alert: "tamper_status".into(),
+                    message: None
+                },
+            ]
+        );
+        assert!(diff_modes(&alerts("Hallway", ""), &alerts("Hallway", "")).is_empty());
+    }
+
+    #[test]
+    fn device_changes() {
+        let previous = HashMap::from([device("RF:01", 0, 1), device("RF:02", 0, 1)]);
+        let current = HashMap::from([device("RF:01", 1, 0), device("RF:03", 0, 1)]);
+
+        let events = diff_devices(&previous, &current);
+        assert_eq!(
+            events,
+            [
+                Event::StateChanged {
+                    sid: "RF:01".into(),
+                    name: "Door RF:01".into(),
+                    state: State::Open
+                },
+                Event::BatteryChanged {
+                    sid: "RF:01".into(),
+                    name: "Door RF:01".into(),
+                    battery: Status::Error
+                },
+                Event::DeviceAdded {
+                    sid: "RF:03".into(),
+                    name: "Door RF:03".into(),
+                    kind: DeviceKind::DoorContact
+                },
+                Event::DeviceRemoved {
+                    sid: "RF:02".into(),
+                    name: "Door RF:02".into()
+                },
+            ]
+        );
+    }
+}